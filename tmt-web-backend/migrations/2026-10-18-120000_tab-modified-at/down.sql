@@ -0,0 +1,7 @@
+-- from 2024-04-12-210654_tab-dates
+CREATE OR REPLACE FUNCTION update_tabs_modified_at() RETURNS trigger AS $$
+BEGIN
+  NEW.modified_at := now();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
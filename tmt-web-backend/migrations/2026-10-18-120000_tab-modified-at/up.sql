@@ -0,0 +1,8 @@
+CREATE OR REPLACE FUNCTION update_tabs_modified_at() RETURNS trigger AS $$
+BEGIN
+  IF (NEW.url, NEW.notes) IS DISTINCT FROM (OLD.url, OLD.notes) THEN
+    NEW.modified_at := now();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
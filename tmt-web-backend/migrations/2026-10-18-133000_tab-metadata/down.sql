@@ -0,0 +1,14 @@
+-- from 2026-10-18-120000_tab-modified-at
+CREATE OR REPLACE FUNCTION update_tabs_modified_at() RETURNS trigger AS $$
+BEGIN
+  IF (NEW.url, NEW.notes) IS DISTINCT FROM (OLD.url, OLD.notes) THEN
+    NEW.modified_at := now();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE tabs DROP COLUMN canonical_url;
+ALTER TABLE tabs DROP COLUMN favicon_url;
+ALTER TABLE tabs DROP COLUMN description;
+ALTER TABLE tabs DROP COLUMN title;
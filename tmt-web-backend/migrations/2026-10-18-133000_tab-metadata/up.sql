@@ -0,0 +1,13 @@
+ALTER TABLE tabs ADD COLUMN title TEXT;
+ALTER TABLE tabs ADD COLUMN description TEXT;
+ALTER TABLE tabs ADD COLUMN favicon_url TEXT;
+ALTER TABLE tabs ADD COLUMN canonical_url TEXT;
+
+CREATE OR REPLACE FUNCTION update_tabs_modified_at() RETURNS trigger AS $$
+BEGIN
+  IF (NEW.url, NEW.notes, NEW.title) IS DISTINCT FROM (OLD.url, OLD.notes, OLD.title) THEN
+    NEW.modified_at := now();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
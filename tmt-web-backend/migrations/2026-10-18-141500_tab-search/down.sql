@@ -0,0 +1,3 @@
+DROP INDEX tabs_search;
+
+ALTER TABLE tabs DROP COLUMN search;
@@ -0,0 +1,8 @@
+ALTER TABLE tabs ADD COLUMN search tsvector GENERATED ALWAYS AS (
+  setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+  setweight(to_tsvector('english', coalesce(notes, '')), 'B') ||
+  -- split urls on punctuation so hosts and path segments become separate words
+  setweight(to_tsvector('english', regexp_replace(url, '[^[:alnum:]]+', ' ', 'g')), 'C')
+) STORED;
+
+CREATE INDEX tabs_search ON tabs USING GIN (search);
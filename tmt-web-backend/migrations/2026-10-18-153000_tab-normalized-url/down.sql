@@ -0,0 +1,2 @@
+DROP INDEX tabs_user_id_normalized_url;
+ALTER TABLE tabs DROP COLUMN normalized_url;
@@ -0,0 +1,64 @@
+ALTER TABLE tabs ADD COLUMN normalized_url TEXT;
+
+-- what `urls::normalize_url` does (short of re-encoding), for filling in existing rows: lowercased
+-- scheme and host, no default port, fragment or tracking parameters, no trailing slash except
+-- on the root path. Anything that isn't an absolute http(s) url is only trimmed.
+CREATE FUNCTION tmp_normalize_tab_url(raw TEXT) RETURNS TEXT AS $$
+DECLARE
+  u TEXT := btrim(raw);
+  parts TEXT[];
+  authority TEXT[];
+  scheme TEXT;
+  port TEXT;
+  path TEXT;
+  kept TEXT[];
+BEGIN
+  parts := regexp_match(u, '^([A-Za-z][A-Za-z0-9+.-]*)://([^/?#]*)([^?#]*)(\?[^#]*)?');
+  IF parts IS NULL OR lower(parts[1]) NOT IN ('http', 'https') THEN
+    RETURN u;
+  END IF;
+  scheme := lower(parts[1]);
+  -- userinfo, host (bracketed for IPv6) and port
+  authority := regexp_match(parts[2], '^(.*@)?(\[[^\]]*\]|[^:]*)(?::([0-9]*))?$');
+  IF authority IS NULL OR authority[2] = '' THEN
+    RETURN u;
+  END IF;
+  port := authority[3];
+  IF port <> '' AND port::numeric > 65535 THEN
+    RETURN u;
+  ELSIF port = '' OR (scheme = 'http' AND port::int = 80) OR (scheme = 'https' AND port::int = 443) THEN
+    port := NULL;
+  ELSIF port IS NOT NULL THEN
+    port := (port::int)::text;
+  END IF;
+  path := rtrim(parts[3], '/');
+  IF path = '' THEN
+    path := '/';
+  END IF;
+  SELECT array_agg(pair ORDER BY n) INTO kept
+  FROM unnest(string_to_array(substr(parts[4], 2), '&')) WITH ORDINALITY AS q(pair, n)
+  WHERE pair <> ''
+    AND lower(split_part(pair, '=', 1)) NOT LIKE 'utm\_%'
+    AND lower(split_part(pair, '=', 1)) NOT IN (
+      'fbclid', 'gclid', 'dclid', 'gbraid', 'wbraid', 'msclkid', 'yclid', 'igshid', 'mc_cid',
+      'mc_eid', '_hsenc', '_hsmi', 'ref_src'
+    );
+  RETURN scheme || '://' || coalesce(authority[1], '') || lower(authority[2])
+    || coalesce(':' || port, '') || path
+    || coalesce('?' || array_to_string(kept, '&'), '');
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- existing duplicates are left alone: only the oldest of them gets a normalized url, so the
+-- unique index can be built, and the others can still be merged into it with
+-- `POST /tabs/:tab_id/merge`.
+UPDATE tabs SET normalized_url = tmp_normalize_tab_url(url)
+WHERE id IN (
+  SELECT DISTINCT ON (user_id, tmp_normalize_tab_url(url)) id
+  FROM tabs
+  ORDER BY user_id, tmp_normalize_tab_url(url), created_at
+);
+
+DROP FUNCTION tmp_normalize_tab_url(TEXT);
+
+CREATE UNIQUE INDEX tabs_user_id_normalized_url ON tabs (user_id, normalized_url);
@@ -0,0 +1,4 @@
+DROP INDEX tags_user_id_created_at_id;
+DROP INDEX tabs_user_id_created_at_id;
+
+ALTER TABLE tags DROP COLUMN created_at;
@@ -0,0 +1,4 @@
+ALTER TABLE tags ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT now();
+
+CREATE INDEX tabs_user_id_created_at_id ON tabs (user_id, created_at, id);
+CREATE INDEX tags_user_id_created_at_id ON tags (user_id, created_at, id);
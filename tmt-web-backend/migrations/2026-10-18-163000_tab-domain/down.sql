@@ -0,0 +1,2 @@
+DROP INDEX tabs_user_id_domain;
+ALTER TABLE tabs DROP COLUMN domain;
@@ -0,0 +1,7 @@
+-- host part of the url, without userinfo or port. IPv6 hosts keep their brackets, as in
+-- `url::Url::host_str`
+ALTER TABLE tabs ADD COLUMN domain TEXT GENERATED ALWAYS AS (
+  lower(substring(url FROM '^[a-zA-Z][a-zA-Z0-9+.-]*://(?:[^/?#@]*@)?(\[[^]/?#@]*\]|[^/?#:\[]+)'))
+) STORED;
+
+CREATE INDEX tabs_user_id_domain ON tabs (user_id, domain);
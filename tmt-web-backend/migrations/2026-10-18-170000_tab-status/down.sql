@@ -0,0 +1,3 @@
+DROP INDEX tabs_user_id_status;
+ALTER TABLE tabs DROP COLUMN status;
+DROP TYPE tab_status;
@@ -0,0 +1,4 @@
+CREATE TYPE tab_status AS ENUM ('unread', 'read', 'archived');
+ALTER TABLE tabs ADD COLUMN status tab_status NOT NULL DEFAULT 'unread';
+
+CREATE INDEX tabs_user_id_status ON tabs (user_id, status);
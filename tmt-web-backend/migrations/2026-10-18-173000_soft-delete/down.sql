@@ -0,0 +1,12 @@
+DROP INDEX tags_deleted_at;
+DROP INDEX tabs_deleted_at;
+DELETE FROM tags WHERE deleted_at IS NOT NULL;
+DELETE FROM tabs WHERE deleted_at IS NOT NULL;
+
+DROP INDEX tags_user_id_tag;
+CREATE UNIQUE INDEX tags_user_id_tag ON tags (user_id, tag);
+DROP INDEX tabs_user_id_normalized_url;
+CREATE UNIQUE INDEX tabs_user_id_normalized_url ON tabs (user_id, normalized_url);
+
+ALTER TABLE tags DROP COLUMN deleted_at;
+ALTER TABLE tabs DROP COLUMN deleted_at;
@@ -0,0 +1,12 @@
+ALTER TABLE tabs ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE tags ADD COLUMN deleted_at TIMESTAMP;
+
+-- trashed rows don't count as duplicates, restoring one can conflict instead
+DROP INDEX tabs_user_id_normalized_url;
+CREATE UNIQUE INDEX tabs_user_id_normalized_url ON tabs (user_id, normalized_url)
+  WHERE deleted_at IS NULL;
+DROP INDEX tags_user_id_tag;
+CREATE UNIQUE INDEX tags_user_id_tag ON tags (user_id, tag) WHERE deleted_at IS NULL;
+
+CREATE INDEX tabs_deleted_at ON tabs (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX tags_deleted_at ON tags (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,4 @@
+DROP INDEX tabs_group_id_group_position;
+ALTER TABLE tabs DROP COLUMN group_position;
+ALTER TABLE tabs DROP COLUMN group_id;
+DROP TABLE tab_groups;
@@ -0,0 +1,15 @@
+CREATE TABLE tab_groups (
+  id TEXT PRIMARY KEY NOT NULL DEFAULT gen_random_uuid (),
+  user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  position INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX tab_groups_user_id_position ON tab_groups (user_id, position);
+
+-- deleting a group leaves its tabs ungrouped unless they're deleted along with it
+ALTER TABLE tabs ADD COLUMN group_id TEXT REFERENCES tab_groups (id) ON DELETE SET NULL;
+ALTER TABLE tabs ADD COLUMN group_position INTEGER;
+
+CREATE INDEX tabs_group_id_group_position ON tabs (group_id, group_position);
@@ -0,0 +1,4 @@
+-- purging a parent from the trash leaves its children at the top level
+ALTER TABLE tags ADD COLUMN parent_id TEXT REFERENCES tags (id) ON DELETE SET NULL;
+
+CREATE INDEX tags_parent_id ON tags (parent_id);
@@ -0,0 +1,2 @@
+DROP INDEX tags_tag_trgm;
+-- pg_trgm stays: it may have been installed before this migration, and other objects may use it
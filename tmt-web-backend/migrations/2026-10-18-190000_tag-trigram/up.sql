@@ -0,0 +1,3 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+-- for both `ILIKE` prefix/substring matches and `<%` in tag autocomplete
+CREATE INDEX tags_tag_trgm ON tags USING gin (tag gin_trgm_ops);
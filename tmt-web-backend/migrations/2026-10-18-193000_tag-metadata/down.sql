@@ -0,0 +1,3 @@
+ALTER TABLE tags DROP COLUMN pinned;
+ALTER TABLE tags DROP COLUMN description;
+ALTER TABLE tags DROP COLUMN color;
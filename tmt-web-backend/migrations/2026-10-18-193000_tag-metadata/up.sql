@@ -0,0 +1,4 @@
+-- colors are stored as lowercase `#rrggbb`
+ALTER TABLE tags ADD COLUMN color TEXT CHECK (color ~ '^#[0-9a-f]{6}$');
+ALTER TABLE tags ADD COLUMN description TEXT;
+ALTER TABLE tags ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,23 @@
+-- from 2024-04-18-153916_fix-check-pwd-fn
+CREATE FUNCTION check_user_pwd(in_email text, pwd text) RETURNS boolean AS $$
+  DECLARE
+    us users;
+  BEGIN
+    SELECT u.* FROM users u WHERE u.email = in_email LIMIT 1 INTO us;
+    RETURN eq_hashed(pwd, us.password);
+  END;
+$$ LANGUAGE plpgsql;
+
+-- from 2024-04-11-162738_user
+CREATE FUNCTION hash_user_pwd_on_create() RETURNS trigger AS $$
+  DECLARE
+    orig_pwd text;
+  BEGIN
+    orig_pwd := NEW.password;
+    NEW.password := hash_pwd(orig_pwd);
+    RETURN NEW;
+  END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER hash_user_pwd_on_create BEFORE INSERT OR UPDATE ON users
+  FOR EACH ROW EXECUTE FUNCTION hash_user_pwd_on_create();
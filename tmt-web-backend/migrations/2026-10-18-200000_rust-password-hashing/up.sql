@@ -0,0 +1,5 @@
+-- passwords are hashed with argon2id by the app now. `eq_hashed` stays to check the md5-crypt
+-- hashes from before, which are replaced as their users log in
+DROP TRIGGER hash_user_pwd_on_create ON users;
+DROP FUNCTION hash_user_pwd_on_create;
+DROP FUNCTION check_user_pwd;
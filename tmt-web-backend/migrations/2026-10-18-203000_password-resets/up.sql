@@ -0,0 +1,9 @@
+-- only a hash of the code that gets mailed out is kept. Rows are deleted once used
+CREATE TABLE password_resets (
+  id TEXT PRIMARY KEY NOT NULL DEFAULT gen_random_uuid (),
+  user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  expires TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT now() + '1 hour'::interval,
+  token_hash TEXT NOT NULL UNIQUE
+);
+
+CREATE INDEX password_resets_user_id ON password_resets (user_id);
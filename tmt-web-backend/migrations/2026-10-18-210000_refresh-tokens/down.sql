@@ -0,0 +1,3 @@
+DROP TABLE refresh_tokens;
+
+ALTER TABLE sessions ALTER COLUMN expires SET DEFAULT now() + '15 minutes'::interval;
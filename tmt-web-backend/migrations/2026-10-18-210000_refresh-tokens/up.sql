@@ -0,0 +1,15 @@
+-- a session now lasts as long as it keeps being refreshed; access tokens are short-lived
+ALTER TABLE sessions ALTER COLUMN expires SET DEFAULT now() + '30 days'::interval;
+
+-- only a hash of each token is kept, so reading the table doesn't hand out sessions. Each refresh
+-- marks the one used and issues the next, so a used one coming back means it leaked and the whole
+-- session goes
+CREATE TABLE refresh_tokens (
+  id TEXT PRIMARY KEY NOT NULL DEFAULT gen_random_uuid (),
+  session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+  created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT now(),
+  used_at TIMESTAMP WITHOUT TIME ZONE,
+  token_hash TEXT NOT NULL UNIQUE
+);
+
+CREATE INDEX refresh_tokens_session_id ON refresh_tokens (session_id);
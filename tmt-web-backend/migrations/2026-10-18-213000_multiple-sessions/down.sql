@@ -0,0 +1,12 @@
+-- keeps each user's most recently used session
+DELETE FROM sessions s
+USING sessions newer
+WHERE s.user_id = newer.user_id
+  AND (s.last_seen_at, s.id) < (newer.last_seen_at, newer.id);
+DROP INDEX sessions_user_id;
+ALTER TABLE sessions
+  DROP COLUMN user_agent,
+  DROP COLUMN ip,
+  DROP COLUMN created_at,
+  DROP COLUMN last_seen_at;
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_id_key UNIQUE (user_id);
@@ -0,0 +1,7 @@
+ALTER TABLE sessions DROP CONSTRAINT sessions_user_id_key;
+ALTER TABLE sessions
+  ADD COLUMN user_agent TEXT,
+  ADD COLUMN ip TEXT,
+  ADD COLUMN created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT now(),
+  ADD COLUMN last_seen_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT now();
+CREATE INDEX sessions_user_id ON sessions(user_id);
@@ -6,7 +6,9 @@ use axum_extra::{
 };
 use diesel::expression::functions::sql_function;
 use diesel::sql_types::Text;
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 
 pub(crate) async fn get_claims(parts: &mut Parts, key: &DecodingKey) -> Result<Claims, AppError> {
     let TypedHeader(Authorization(bearer)) = parts
@@ -18,8 +20,12 @@ pub(crate) async fn get_claims(parts: &mut Parts, key: &DecodingKey) -> Result<C
         })?;
     let validation = Validation::new(Algorithm::HS256);
     let token_data = decode::<Claims>(bearer.token(), key, &validation).map_err(|e| {
-        tracing::error!("error decoding token: {:?}", e);
-        AppError::InvalidToken
+        if let ErrorKind::ExpiredSignature = e.kind() {
+            AppError::ExpiredToken
+        } else {
+            tracing::error!("error decoding token: {:?}", e);
+            AppError::InvalidToken
+        }
     })?;
     let claims = token_data.claims;
     Ok(claims)
@@ -33,4 +39,4 @@ pub(crate) fn encode_jwt(claims: Claims, key: &EncodingKey) -> Result<String, Ap
     encode(&headers, &claims, key).map_err(|_| AppError::TokenCreation)
 }
 
-sql_function!(fn check_user_pwd(email: Text, pwd: Text) -> Bool);
+sql_function!(fn eq_hashed(val: Text, hashed: Text) -> Bool);
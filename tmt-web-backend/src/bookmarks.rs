@@ -0,0 +1,278 @@
+use chrono::{DateTime, NaiveDateTime};
+use scraper::{ElementRef, Html};
+
+// A link from a Netscape bookmark file (the format every browser exports), e.g.
+//
+//     <DL><p>
+//         <DT><H3 ADD_DATE="1712345678">rust</H3>
+//         <DL><p>
+//             <DT><A HREF="https://example.com" ADD_DATE="1712345678" TAGS="a,b">title</A>
+//             <DD>description
+//         </DL><p>
+//     </DL><p>
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Bookmark {
+    pub url: String,
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub add_date: Option<NaiveDateTime>,
+    pub last_modified: Option<NaiveDateTime>,
+    // enclosing folders, outermost first
+    pub folders: Vec<String>,
+    // from the `TAGS` attribute some exporters (e.g. Firefox, Pinboard) add
+    pub tags: Vec<String>,
+}
+
+pub fn parse_bookmarks(html: &str) -> Vec<Bookmark> {
+    let doc = Html::parse_document(html);
+    let mut bookmarks = Vec::new();
+    walk(doc.root_element(), &mut Vec::new(), &mut bookmarks);
+    bookmarks
+}
+
+// Browsers disagree on whether a folder's `<DL>` ends up inside its `<DT>` or after it once
+// the (unclosed) tags are parsed, so both are handled.
+fn walk(el: ElementRef, folders: &mut Vec<String>, out: &mut Vec<Bookmark>) {
+    let mut pending_folder: Option<String> = None;
+    // whether the last `<DT>` was a link, so a following `<DD>` describes it
+    let mut last_was_link = false;
+    for child in el.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "dt" => {
+                last_was_link = false;
+                let heading = child_elements(child, "h3").next();
+                let link = child_elements(child, "a").next();
+                if let Some(h3) = heading {
+                    let name = clean_text(&h3.text().collect::<String>());
+                    let mut lists = child_elements(child, "dl").peekable();
+                    if lists.peek().is_none() {
+                        pending_folder = Some(name);
+                    } else {
+                        folders.push(name);
+                        lists.for_each(|dl| walk(dl, folders, out));
+                        folders.pop();
+                    }
+                } else if let Some(a) = link {
+                    if let Some(bookmark) = parse_link(a, folders) {
+                        out.push(bookmark);
+                        last_was_link = true;
+                    }
+                } else {
+                    walk(child, folders, out);
+                }
+            }
+            "dd" => {
+                if last_was_link {
+                    let own_text: String = child
+                        .children()
+                        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+                        .collect();
+                    let notes = clean_text(&own_text);
+                    if let Some(bookmark) = out.last_mut() {
+                        bookmark.notes = Some(notes).filter(|n| !n.is_empty());
+                    }
+                }
+                last_was_link = false;
+                // folder descriptions can wrap the folder's list
+                walk(child, folders, out);
+            }
+            "dl" => match pending_folder.take() {
+                Some(name) => {
+                    folders.push(name);
+                    walk(child, folders, out);
+                    folders.pop();
+                }
+                None => walk(child, folders, out),
+            },
+            _ => walk(child, folders, out),
+        }
+    }
+}
+
+fn child_elements<'a>(el: ElementRef<'a>, name: &'a str) -> impl Iterator<Item = ElementRef<'a>> {
+    el.children()
+        .filter_map(ElementRef::wrap)
+        .filter(move |c| c.value().name() == name)
+}
+
+fn parse_link(a: ElementRef, folders: &[String]) -> Option<Bookmark> {
+    let attrs = a.value();
+    let url = attrs.attr("href")?.trim().to_string();
+    if url.is_empty() {
+        return None;
+    }
+    let title = clean_text(&a.text().collect::<String>());
+    let tags = attrs
+        .attr("tags")
+        .map(|ts| {
+            ts.split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Bookmark {
+        url,
+        title: Some(title).filter(|t| !t.is_empty()),
+        notes: None,
+        add_date: attrs.attr("add_date").and_then(parse_timestamp),
+        last_modified: attrs.attr("last_modified").and_then(parse_timestamp),
+        folders: folders.to_vec(),
+        tags,
+    })
+}
+
+// seconds, though some exporters write milli- or microseconds
+fn parse_timestamp(ts: &str) -> Option<NaiveDateTime> {
+    let n = ts.trim().parse::<i64>().ok()?;
+    let dt = if n > 100_000_000_000_000 {
+        DateTime::from_timestamp_micros(n)
+    } else if n > 100_000_000_000 {
+        DateTime::from_timestamp_millis(n)
+    } else {
+        DateTime::from_timestamp(n, 0)
+    };
+    dt.map(|dt| dt.naive_utc())
+}
+
+fn clean_text(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub const HEADER: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file. -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+"#;
+
+pub const FOOTER: &str = "</DL><p>\n";
+
+pub fn render_folder_start(name: &str) -> String {
+    format!("    <DT><H3>{}</H3>\n    <DL><p>\n", escape_html(name))
+}
+
+pub fn render_folder_end() -> &'static str {
+    "    </DL><p>\n"
+}
+
+// `folders` is ignored, where a link goes is up to the caller
+pub fn render_link(bookmark: &Bookmark, indent: usize) -> String {
+    let pad = " ".repeat(indent * 4);
+    let mut attrs = format!(r#"HREF="{}""#, escape_html(&bookmark.url));
+    if let Some(added) = bookmark.add_date {
+        attrs.push_str(&format!(r#" ADD_DATE="{}""#, added.and_utc().timestamp()));
+    }
+    if let Some(modified) = bookmark.last_modified {
+        attrs.push_str(&format!(
+            r#" LAST_MODIFIED="{}""#,
+            modified.and_utc().timestamp()
+        ));
+    }
+    if !bookmark.tags.is_empty() {
+        attrs.push_str(&format!(
+            r#" TAGS="{}""#,
+            escape_html(&bookmark.tags.join(","))
+        ));
+    }
+    let title = bookmark.title.as_deref().unwrap_or(&bookmark.url);
+    let mut link = format!("{}<DT><A {}>{}</A>\n", pad, attrs, escape_html(title));
+    if let Some(notes) = &bookmark.notes {
+        link.push_str(&format!("{}<DD>{}\n", pad, escape_html(notes)));
+    }
+    link
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1712345678">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1712345678">Rust</A>
+        <DD>the language
+        <DT><H3>lang</H3>
+        <DL><p>
+            <DT><A HREF="https://go.dev" ADD_DATE="1712345678000" TAGS="go, google">Go</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="https://example.com">  Example
+      Domain </A>
+</DL><p>
+"#;
+
+    #[test]
+    fn test_parse_bookmarks() {
+        let bookmarks = parse_bookmarks(EXPORT);
+        let added = DateTime::from_timestamp(1712345678, 0).unwrap().naive_utc();
+        assert_eq!(
+            bookmarks,
+            vec![
+                Bookmark {
+                    url: "https://www.rust-lang.org/".into(),
+                    title: Some("Rust".into()),
+                    notes: Some("the language".into()),
+                    add_date: Some(added),
+                    folders: vec!["Bookmarks bar".into()],
+                    ..Default::default()
+                },
+                Bookmark {
+                    url: "https://go.dev".into(),
+                    title: Some("Go".into()),
+                    add_date: Some(added),
+                    folders: vec!["Bookmarks bar".into(), "lang".into()],
+                    tags: vec!["go".into(), "google".into()],
+                    ..Default::default()
+                },
+                Bookmark {
+                    url: "https://example.com".into(),
+                    title: Some("Example Domain".into()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let added = DateTime::from_timestamp(1712345678, 0).unwrap().naive_utc();
+        let bookmark = Bookmark {
+            url: "https://example.com/?a=1&b=2".into(),
+            title: Some("<Example> & \"friends\"".into()),
+            notes: Some("some notes".into()),
+            add_date: Some(added),
+            last_modified: Some(added),
+            folders: vec!["a folder".into()],
+            tags: vec!["a folder".into(), "other".into()],
+        };
+        let html = [
+            HEADER.to_string(),
+            render_folder_start("a folder"),
+            render_link(&bookmark, 2),
+            render_folder_end().to_string(),
+            FOOTER.to_string(),
+        ]
+        .concat();
+        assert_eq!(parse_bookmarks(&html), vec![bookmark]);
+    }
+
+    #[test]
+    fn test_parse_bookmarks_not_bookmarks() {
+        assert!(parse_bookmarks("<html><body><p>hello</p></body></html>").is_empty());
+        assert!(parse_bookmarks("").is_empty());
+    }
+}
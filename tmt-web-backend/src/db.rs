@@ -1,3 +1,5 @@
+pub(crate) mod groups;
+pub(crate) mod import;
 pub(crate) mod sessions;
 pub(crate) mod tabs;
 pub(crate) mod tags;
@@ -9,30 +11,75 @@ use tracing::error;
 #[cfg(test)]
 pub use util::test_util;
 
-use crate::{auth::check_user_pwd, types::AppError};
-use util::err_is_deserialization_unexpected_null;
+use crate::{
+    auth::eq_hashed, passwords::PasswordHasher, schema::users::dsl as users_dsl, types::AppError,
+};
+use util::err_is_not_found;
 
+// Legacy md5-crypt hashes are checked by pgcrypto. Either kind is replaced with a fresh argon2id
+// hash once the password is known to be right, if it wasn't made with the current settings.
 pub async fn validate_password(
     conn: Connection,
+    hasher: &PasswordHasher,
     email: String,
     password: String,
 ) -> Result<bool, AppError> {
-    // TODO(SHR): `DeserializationError(UnexpectedNullError)` here seems to indicate no user &
-    // should be handled as such
-    let result = conn
-        .interact(|conn| select(check_user_pwd(email, password)).load(conn))
+    let (user_id, hash): (String, String) = conn
+        .interact(|conn| {
+            users_dsl::users
+                .filter(users_dsl::email.eq(email))
+                .select((users_dsl::id, users_dsl::password))
+                .first(conn)
+        })
         .await
         .map_err(|e| {
             error!("validate_password db error: {:?}", e);
             AppError::DBError
         })?
         .map_err(|e| {
-            if err_is_deserialization_unexpected_null(&e) {
+            if err_is_not_found(&e) {
                 AppError::NotFound
             } else {
                 error!("validate_password db error: {:?}", e);
                 AppError::DBError
             }
         })?;
-    Ok(result[0])
+    let valid = if PasswordHasher::is_legacy(&hash) {
+        let (pwd, h) = (password.clone(), hash.clone());
+        conn.interact(|conn| select(eq_hashed(pwd, h)).get_result(conn))
+            .await
+            .map_err(|e| {
+                error!("validate_password db error: {:?}", e);
+                AppError::DBError
+            })?
+            .map_err(|e| {
+                error!("validate_password db error: {:?}", e);
+                AppError::DBError
+            })?
+    } else {
+        hasher.verify(password.clone(), hash.clone()).await?
+    };
+    if valid && hasher.needs_rehash(&hash) {
+        let new_hash = hasher.hash(password).await?;
+        // a concurrent password change wins
+        conn.interact(|conn| {
+            diesel::update(
+                users_dsl::users
+                    .filter(users_dsl::id.eq(user_id))
+                    .filter(users_dsl::password.eq(hash)),
+            )
+            .set(users_dsl::password.eq(new_hash))
+            .execute(conn)
+        })
+        .await
+        .map_err(|e| {
+            error!("error rehashing password: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            error!("error rehashing password: {:?}", e);
+            AppError::DBError
+        })?;
+    }
+    Ok(valid)
 }
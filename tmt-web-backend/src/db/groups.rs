@@ -0,0 +1,383 @@
+use deadpool_diesel::postgres::{Connection, Pool};
+use diesel::{dsl::now, prelude::*, Connection as _};
+use futures_util::stream::{self, StreamExt};
+
+use std::collections::HashSet;
+
+use crate::{
+    db::{
+        tabs::duplicate_tab_id,
+        util::{err_is_not_found, get_conn},
+    },
+    metadata::{MetadataFetcher, PageMetadata},
+    models::{
+        group::{NewGroupTab, NewTabGroup, NewTabGroupRecord, TabGroup, TabGroupWithTabs},
+        tab::{NewTab, NewTabRecord, Tab},
+    },
+    schema::{
+        tab_groups::{self, dsl as groups_dsl},
+        tabs::{self, dsl as tabs_dsl},
+    },
+    types::AppError,
+    urls::normalize_url,
+};
+
+const MAX_CONCURRENT_FETCHES: usize = 4;
+
+// urls the user already has a live tab for are moved into the group rather than duplicated
+pub async fn new_group(
+    pool: Pool,
+    fetcher: &dyn MetadataFetcher,
+    data: NewTabGroup,
+) -> Result<TabGroupWithTabs, AppError> {
+    let NewTabGroup {
+        user_id,
+        name,
+        tabs,
+    } = data;
+    let mut seen = HashSet::new();
+    let tabs: Vec<(String, NewGroupTab)> = tabs
+        .into_iter()
+        .map(|t| (normalize_url(&t.url), t))
+        .filter(|(normalized, _)| seen.insert(normalized.clone()))
+        .collect();
+    let uid = user_id.clone();
+    let normalized: Vec<String> = tabs.iter().map(|(n, _)| n.clone()).collect();
+    let conn = get_conn(pool.clone()).await?;
+    let existing: HashSet<String> = conn
+        .interact(move |conn| {
+            tabs_dsl::tabs
+                .filter(tabs_dsl::user_id.eq(uid))
+                .filter(tabs_dsl::normalized_url.eq_any(normalized))
+                .filter(tabs_dsl::deleted_at.is_null())
+                .select(tabs_dsl::normalized_url.assume_not_null())
+                .load::<String>(conn)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("error looking up group tabs: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error looking up group tabs: {:?}", e);
+            AppError::DBError
+        })?
+        .into_iter()
+        .collect();
+    // fetch before checking out a connection, and only for tabs that will be created. A few at a
+    // time, so one request can't set off a flood of outgoing ones
+    let to_fetch: Vec<(usize, Option<String>)> = tabs
+        .iter()
+        .map(|(normalized, tab)| (!existing.contains(normalized)).then(|| tab.url.clone()))
+        .enumerate()
+        .collect();
+    let mut metadata: Vec<(usize, PageMetadata)> = stream::iter(to_fetch)
+        .map(|(i, url)| async move {
+            let Some(url) = url else {
+                return (i, PageMetadata::default());
+            };
+            let md = fetcher.fetch(&url).await.unwrap_or_else(|e| {
+                tracing::warn!("error fetching metadata for {:?}: {:?}", &url, e);
+                PageMetadata::default()
+            });
+            (i, md)
+        })
+        .buffer_unordered(MAX_CONCURRENT_FETCHES)
+        .collect()
+        .await;
+    metadata.sort_by_key(|(i, _)| *i);
+    let metadata = metadata.into_iter().map(|(_, md)| md);
+    let conn = get_conn(pool).await?;
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let position = groups_dsl::tab_groups
+                .filter(groups_dsl::user_id.eq(&user_id))
+                .select(diesel::dsl::max(groups_dsl::position))
+                .first::<Option<i32>>(conn)?
+                .map_or(0, |p| p + 1);
+            let group = diesel::insert_into(tab_groups::table)
+                .values(NewTabGroupRecord {
+                    user_id: user_id.clone(),
+                    name,
+                    position,
+                })
+                .returning(TabGroup::as_returning())
+                .get_result(conn)?;
+            for (i, ((normalized, tab), metadata)) in tabs.into_iter().zip(metadata).enumerate() {
+                let group_position = Some(i as i32);
+                let tab_id = match duplicate_tab_id(conn, &user_id, &normalized, None)? {
+                    Some(id) => Some(id),
+                    None => {
+                        let mut record = NewTabRecord::new(
+                            NewTab {
+                                user_id: user_id.clone(),
+                                url: tab.url,
+                                notes: tab.notes,
+                                title: tab.title,
+                            },
+                            metadata,
+                        );
+                        record.group_id = Some(group.id.clone());
+                        record.group_position = group_position;
+                        diesel::insert_into(tabs::table)
+                            .values(record)
+                            .on_conflict_do_nothing()
+                            .returning(tabs_dsl::id)
+                            .get_result::<String>(conn)
+                            .optional()?
+                            .map_or_else(
+                                // inserted by someone else since the lookup
+                                || duplicate_tab_id(conn, &user_id, &normalized, None),
+                                |_| Ok(None),
+                            )?
+                    }
+                };
+                if let Some(tab_id) = tab_id {
+                    diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(tab_id)))
+                        .set((
+                            tabs_dsl::group_id.eq(&group.id),
+                            tabs_dsl::group_position.eq(group_position),
+                        ))
+                        .execute(conn)?;
+                }
+            }
+            let tabs = group_tabs(conn, &group.id)?;
+            Ok(TabGroupWithTabs { group, tabs })
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error creating tab group: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error creating tab group: {:?}", e);
+        AppError::DBError
+    })
+}
+
+fn group_tabs(conn: &mut PgConnection, group_id: &str) -> QueryResult<Vec<Tab>> {
+    tabs_dsl::tabs
+        .filter(tabs_dsl::group_id.eq(group_id))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .order((tabs_dsl::group_position.asc(), tabs_dsl::id.asc()))
+        .select(Tab::as_select())
+        .load(conn)
+}
+
+fn get_user_group(conn: &mut PgConnection, user_id: &str, group_id: &str) -> QueryResult<TabGroup> {
+    groups_dsl::tab_groups
+        .filter(groups_dsl::id.eq(group_id))
+        .filter(groups_dsl::user_id.eq(user_id))
+        .select(TabGroup::as_select())
+        .first(conn)
+}
+
+pub async fn get_user_groups(conn: Connection, user_id: String) -> Result<Vec<TabGroup>, AppError> {
+    conn.interact(|conn| {
+        groups_dsl::tab_groups
+            .filter(groups_dsl::user_id.eq(user_id))
+            .order((groups_dsl::position.asc(), groups_dsl::id.asc()))
+            .select(TabGroup::as_select())
+            .load(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error retrieving tab groups: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error retrieving tab groups: {:?}", e);
+        AppError::DBError
+    })
+}
+
+pub async fn get_group_with_tabs(
+    conn: Connection,
+    user_id: String,
+    group_id: String,
+) -> Result<TabGroupWithTabs, AppError> {
+    conn.interact(move |conn| {
+        let group = get_user_group(conn, &user_id, &group_id)?;
+        let tabs = group_tabs(conn, &group.id)?;
+        Ok(TabGroupWithTabs { group, tabs })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error retrieving tab group: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error retrieving tab group: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+pub async fn rename_group(
+    conn: Connection,
+    user_id: String,
+    group_id: String,
+    name: String,
+) -> Result<TabGroup, AppError> {
+    conn.interact(|conn| {
+        diesel::update(
+            groups_dsl::tab_groups
+                .filter(groups_dsl::id.eq(group_id))
+                .filter(groups_dsl::user_id.eq(user_id)),
+        )
+        .set(groups_dsl::name.eq(name))
+        .returning(TabGroup::as_returning())
+        .get_result(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error renaming tab group: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error renaming tab group: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+// `group_ids` has to list every one of the user's groups exactly once
+pub async fn reorder_groups(
+    conn: Connection,
+    user_id: String,
+    group_ids: Vec<String>,
+) -> Result<Vec<TabGroup>, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let current: HashSet<String> = groups_dsl::tab_groups
+                .filter(groups_dsl::user_id.eq(&user_id))
+                .select(groups_dsl::id)
+                .load::<String>(conn)?
+                .into_iter()
+                .collect();
+            if !is_permutation(&current, &group_ids) {
+                return Ok(Err(AppError::BadRequest));
+            }
+            for (position, group_id) in group_ids.iter().enumerate() {
+                diesel::update(groups_dsl::tab_groups.filter(groups_dsl::id.eq(group_id)))
+                    .set(groups_dsl::position.eq(position as i32))
+                    .execute(conn)?;
+            }
+            groups_dsl::tab_groups
+                .filter(groups_dsl::user_id.eq(&user_id))
+                .order((groups_dsl::position.asc(), groups_dsl::id.asc()))
+                .select(TabGroup::as_select())
+                .load(conn)
+                .map(Ok)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error reordering tab groups: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error reordering tab groups: {:?}", e);
+        AppError::DBError
+    })?
+}
+
+// `tab_ids` has to list every live tab in the group exactly once
+pub async fn reorder_group_tabs(
+    conn: Connection,
+    user_id: String,
+    group_id: String,
+    tab_ids: Vec<String>,
+) -> Result<TabGroupWithTabs, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let group = get_user_group(conn, &user_id, &group_id)?;
+            let current: HashSet<String> = group_tabs(conn, &group.id)?
+                .into_iter()
+                .map(|t| t.id)
+                .collect();
+            if !is_permutation(&current, &tab_ids) {
+                return Ok(Err(AppError::BadRequest));
+            }
+            for (position, tab_id) in tab_ids.iter().enumerate() {
+                diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(tab_id)))
+                    .set(tabs_dsl::group_position.eq(position as i32))
+                    .execute(conn)?;
+            }
+            let tabs = group_tabs(conn, &group.id)?;
+            Ok(Ok(TabGroupWithTabs { group, tabs }))
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error reordering group tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error reordering group tabs: {:?}", e);
+            AppError::DBError
+        }
+    })?
+}
+
+fn is_permutation(current: &HashSet<String>, ids: &[String]) -> bool {
+    let requested: HashSet<&String> = ids.iter().collect();
+    requested.len() == ids.len()
+        && requested.len() == current.len()
+        && requested.iter().all(|id| current.contains(*id))
+}
+
+// without `delete_tabs` the group's tabs are kept, ungrouped
+pub async fn delete_group(
+    conn: Connection,
+    user_id: String,
+    group_id: String,
+    delete_tabs: bool,
+) -> Result<(), AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let group = get_user_group(conn, &user_id, &group_id)?;
+            if delete_tabs {
+                diesel::update(
+                    tabs_dsl::tabs
+                        .filter(tabs_dsl::group_id.eq(&group.id))
+                        .filter(tabs_dsl::deleted_at.is_null()),
+                )
+                .set(tabs_dsl::deleted_at.eq(now))
+                .execute(conn)?;
+            }
+            diesel::update(tabs_dsl::tabs.filter(tabs_dsl::group_id.eq(&group.id)))
+                .set((
+                    tabs_dsl::group_id.eq(None::<String>),
+                    tabs_dsl::group_position.eq(None::<i32>),
+                ))
+                .execute(conn)?;
+            diesel::delete(groups_dsl::tab_groups.filter(groups_dsl::id.eq(&group.id)))
+                .execute(conn)
+                .map(|_| ())
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error deleting tab group: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error deleting tab group: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
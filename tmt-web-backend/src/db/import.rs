@@ -0,0 +1,196 @@
+use deadpool_diesel::postgres::Connection;
+use diesel::{prelude::*, Connection as _};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    bookmarks::Bookmark,
+    db::tags::insert_tags,
+    models::{
+        tab::{ImportDuplicate, ImportReport, NewTabRecord, NewTabTag},
+        tag::NewTag,
+    },
+    schema::{
+        tabs::{self, dsl as tabs_dsl},
+        tabs_tags,
+        tags::dsl as tags_dsl,
+    },
+    types::AppError,
+    urls::normalize_url,
+};
+
+// keeps each insert well under postgres' limit on bind parameters
+const CHUNK_SIZE: usize = 1000;
+
+struct PendingTab {
+    normalized_url: String,
+    bookmark: Bookmark,
+    tags: Vec<String>,
+}
+
+pub async fn import_bookmarks(
+    conn: Connection,
+    user_id: String,
+    bookmarks: Vec<Bookmark>,
+    dry_run: bool,
+) -> Result<ImportReport, AppError> {
+    conn.interact(move |conn| conn.transaction(|conn| import(conn, &user_id, bookmarks, dry_run)))
+        .await
+        .map_err(|e| {
+            tracing::error!("error importing bookmarks: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error importing bookmarks: {:?}", e);
+            AppError::DBError
+        })
+}
+
+fn import(
+    conn: &mut PgConnection,
+    user_id: &str,
+    bookmarks: Vec<Bookmark>,
+    dry_run: bool,
+) -> QueryResult<ImportReport> {
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    // the first copy of a url in the file wins, but gets every folder the copies were in
+    let mut pending: Vec<PendingTab> = Vec::new();
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+    for bookmark in bookmarks {
+        if !matches!(url::Url::parse(&bookmark.url), Ok(u) if matches!(u.scheme(), "http" | "https"))
+        {
+            report.skipped.push(bookmark.url);
+            continue;
+        }
+        let normalized_url = normalize_url(&bookmark.url);
+        let tags: Vec<String> = bookmark
+            .folders
+            .iter()
+            .chain(bookmark.tags.iter())
+            // an unnamed folder doesn't make a tag
+            .filter(|t| !t.trim().is_empty())
+            .cloned()
+            .collect();
+        match by_url.get(&normalized_url) {
+            Some(&i) => {
+                let first = &mut pending[i];
+                for tag in tags {
+                    if !first.tags.contains(&tag) {
+                        first.tags.push(tag);
+                    }
+                }
+                report.duplicates.push(ImportDuplicate {
+                    url: bookmark.url,
+                    existing_tab_id: None,
+                });
+            }
+            None => {
+                by_url.insert(normalized_url.clone(), pending.len());
+                let mut deduped: Vec<String> = Vec::with_capacity(tags.len());
+                for tag in tags {
+                    if !deduped.contains(&tag) {
+                        deduped.push(tag);
+                    }
+                }
+                pending.push(PendingTab {
+                    normalized_url,
+                    bookmark,
+                    tags: deduped,
+                });
+            }
+        }
+    }
+
+    let existing: HashMap<String, String> = tabs_dsl::tabs
+        .filter(tabs_dsl::user_id.eq(user_id))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .filter(tabs_dsl::normalized_url.eq_any(by_url.keys().cloned().collect::<Vec<_>>()))
+        .select((tabs_dsl::normalized_url.assume_not_null(), tabs_dsl::id))
+        .load::<(String, String)>(conn)?
+        .into_iter()
+        .collect();
+    pending.retain_mut(|p| match existing.get(&p.normalized_url) {
+        Some(id) => {
+            report.duplicates.push(ImportDuplicate {
+                url: std::mem::take(&mut p.bookmark.url),
+                existing_tab_id: Some(id.clone()),
+            });
+            false
+        }
+        None => true,
+    });
+
+    let tag_names: BTreeSet<String> = pending.iter().flat_map(|p| p.tags.clone()).collect();
+    let mut tag_ids: HashMap<String, String> = tags_dsl::tags
+        .filter(tags_dsl::user_id.eq(user_id))
+        .filter(tags_dsl::deleted_at.is_null())
+        .filter(tags_dsl::tag.eq_any(tag_names.iter().cloned().collect::<Vec<_>>()))
+        .select((tags_dsl::tag, tags_dsl::id))
+        .load::<(String, String)>(conn)?
+        .into_iter()
+        .collect();
+    let new_tags: Vec<NewTag> = tag_names
+        .into_iter()
+        .filter(|t| !tag_ids.contains_key(t))
+        .map(|tag| NewTag {
+            user_id: user_id.to_string(),
+            tag,
+        })
+        .collect();
+
+    report.new_tabs = pending.len();
+    report.new_tags = new_tags.len();
+    if dry_run {
+        return Ok(report);
+    }
+
+    for chunk in new_tags.chunks(CHUNK_SIZE) {
+        for tag in insert_tags(conn, chunk)? {
+            tag_ids.insert(tag.tag, tag.id);
+        }
+    }
+    let tags_by_url: HashMap<&str, &[String]> = pending
+        .iter()
+        .map(|p| (p.normalized_url.as_str(), p.tags.as_slice()))
+        .collect();
+    let mut tab_tags: Vec<NewTabTag> = Vec::new();
+    for chunk in pending.chunks(CHUNK_SIZE) {
+        let records: Vec<NewTabRecord> = chunk
+            .iter()
+            .map(|p| NewTabRecord {
+                user_id: user_id.to_string(),
+                url: p.bookmark.url.clone(),
+                notes: p.bookmark.notes.clone(),
+                title: p.bookmark.title.clone(),
+                description: None,
+                favicon_url: None,
+                canonical_url: None,
+                normalized_url: Some(p.normalized_url.clone()),
+                created_at: p.bookmark.add_date,
+                modified_at: p.bookmark.last_modified.or(p.bookmark.add_date),
+                group_id: None,
+                group_position: None,
+            })
+            .collect();
+        let inserted: Vec<(String, Option<String>)> = diesel::insert_into(tabs::table)
+            .values(records)
+            .returning((tabs_dsl::id, tabs_dsl::normalized_url))
+            .get_results(conn)?;
+        for (tab_id, normalized_url) in inserted {
+            let tags = tags_by_url[normalized_url.unwrap_or_default().as_str()];
+            tab_tags.extend(tags.iter().map(|tag| NewTabTag {
+                tab_id: tab_id.clone(),
+                tag_id: tag_ids[tag].clone(),
+            }));
+        }
+    }
+    for chunk in tab_tags.chunks(CHUNK_SIZE) {
+        diesel::insert_into(tabs_tags::table)
+            .values(chunk)
+            .execute(conn)?;
+    }
+    Ok(report)
+}
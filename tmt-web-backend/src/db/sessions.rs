@@ -1,19 +1,29 @@
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use deadpool_diesel::postgres::{Connection, Pool};
-use diesel::prelude::*;
+use diesel::{prelude::*, Connection as _};
 use tracing::error;
 
 use super::util::get_conn;
 use crate::{
-    models::session::{NewSession, Session},
+    models::session::{ClientInfo, NewRefreshToken, NewSession, RefreshToken, Session},
     schema::{
+        refresh_tokens::{self, dsl as refresh_tokens_dsl},
         sessions::{self, dsl as sessions_dsl},
         users::dsl as users_dsl,
     },
+    tokens::{hash_secret, new_secret},
     types::{AppError, Claims},
 };
 
-pub async fn new_session(pool: Pool, user_email: String) -> Result<Session, AppError> {
+// how long a session lasts without its refresh token being used
+pub const SESSION_TTL_DAYS: i64 = 30;
+
+// Every login gets a session of its own; the user's expired ones are cleared out on the way
+pub async fn new_session(
+    pool: Pool,
+    user_email: String,
+    ClientInfo { user_agent, ip }: ClientInfo,
+) -> Result<Session, AppError> {
     let conn = get_conn(pool).await?;
     let user_id: String = conn
         .interact(|conn| {
@@ -32,47 +42,116 @@ pub async fn new_session(pool: Pool, user_email: String) -> Result<Session, AppE
             error!("error retrieving user id: {:?}", e);
             AppError::DBError
         })?;
-    let uid = user_id.clone();
-    if let Some(s) = conn
-        .interact(|conn| {
+    let new_sess = NewSession {
+        user_id,
+        user_agent,
+        ip,
+    };
+    conn.interact(|conn| {
+        let now = Utc::now().naive_utc();
+        diesel::delete(
             sessions_dsl::sessions
-                .filter(sessions_dsl::user_id.eq(uid))
-                .select(Session::as_select())
+                .filter(sessions_dsl::user_id.eq(&new_sess.user_id))
+                .filter(sessions_dsl::expires.le(now)),
+        )
+        .execute(conn)?;
+        diesel::insert_into(sessions::table)
+            .values(new_sess)
+            .returning(Session::as_returning())
+            .get_result(conn)
+    })
+    .await
+    .map_err(|e| {
+        error!("error creating session: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        error!("error creating session: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// the token is only returned here, the database just gets its hash
+pub async fn new_refresh_token(conn: Connection, session_id: String) -> Result<String, AppError> {
+    let token = new_secret();
+    let token_hash = hash_secret(&token);
+    conn.interact(|conn| {
+        diesel::insert_into(refresh_tokens::table)
+            .values(NewRefreshToken {
+                session_id,
+                token_hash,
+            })
+            .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        error!("error creating refresh token: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        error!("error creating refresh token: {:?}", e);
+        AppError::DBError
+    })?;
+    Ok(token)
+}
+
+// Trades a refresh token for a new one and pushes the session's expiry back. A token can only be
+// used once: presenting one that already was means it leaked, so the whole session goes
+pub async fn refresh_session(
+    conn: Connection,
+    token: String,
+) -> Result<(Session, String), AppError> {
+    let token_hash = hash_secret(&token);
+    let new_token = new_secret();
+    let new_token_hash = hash_secret(&new_token);
+    conn.interact(|conn| {
+        let now = Utc::now().naive_utc();
+        conn.transaction(|conn| {
+            let Some(old) = refresh_tokens_dsl::refresh_tokens
+                .filter(refresh_tokens_dsl::token_hash.eq(token_hash))
+                .select(RefreshToken::as_select())
+                .for_update()
                 .first(conn)
-                .optional()
+                .optional()?
+            else {
+                return Ok(Err(AppError::InvalidToken));
+            };
+            let session = sessions_dsl::sessions.filter(sessions_dsl::id.eq(&old.session_id));
+            if old.used_at.is_some() {
+                tracing::warn!("refresh token reused, revoking session {}", old.session_id);
+                diesel::delete(session).execute(conn)?;
+                return Ok(Err(AppError::InvalidToken));
+            }
+            let sess = session.select(Session::as_select()).first(conn)?;
+            if session_expired(&sess) {
+                diesel::delete(session).execute(conn)?;
+                return Ok(Err(AppError::ExpiredToken));
+            }
+            diesel::update(refresh_tokens_dsl::refresh_tokens.find(&old.id))
+                .set(refresh_tokens_dsl::used_at.eq(now))
+                .execute(conn)?;
+            let sess = diesel::update(session)
+                .set(sessions_dsl::expires.eq(now + Duration::days(SESSION_TTL_DAYS)))
+                .returning(Session::as_returning())
+                .get_result(conn)?;
+            diesel::insert_into(refresh_tokens::table)
+                .values(NewRefreshToken {
+                    session_id: old.session_id,
+                    token_hash: new_token_hash,
+                })
+                .execute(conn)?;
+            Ok(Ok((sess, new_token)))
         })
-        .await
-        .map_err(|e| {
-            error!("error retrieving session: {:?}", e);
-            AppError::DBError
-        })?
-        .map_err(|e| {
-            error!("error retrieving session: {:?}", e);
-            AppError::DBError
-        })?
-    {
-        tracing::info!("session: {:?}", &s);
-        Ok(s)
-    } else {
-        let new_sess = NewSession { user_id };
-        let created = conn
-            .interact(|conn| {
-                diesel::insert_into(sessions::table)
-                    .values(new_sess)
-                    .returning(Session::as_returning())
-                    .get_result(conn)
-                    .map_err(|e| {
-                        error!("error creating session: {:?}", e);
-                        AppError::DBError
-                    })
-            })
-            .await
-            .map_err(|e| {
-                error!("error creating session: {:?}", e);
-                AppError::DBError
-            })??;
-        Ok(created)
-    }
+    })
+    .await
+    .map_err(|e| {
+        error!("error refreshing session: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        error!("error refreshing session: {:?}", e);
+        AppError::DBError
+    })?
 }
 
 pub async fn delete_session(conn: Connection, session_id: String) -> Result<(), AppError> {
@@ -87,6 +166,78 @@ pub async fn delete_session(conn: Connection, session_id: String) -> Result<(),
     Ok(())
 }
 
+// live sessions only, most recently used first
+pub async fn get_user_sessions(
+    conn: Connection,
+    user_id: String,
+) -> Result<Vec<Session>, AppError> {
+    conn.interact(|conn| {
+        sessions_dsl::sessions
+            .filter(sessions_dsl::user_id.eq(user_id))
+            .filter(sessions_dsl::expires.gt(Utc::now().naive_utc()))
+            .order((sessions_dsl::last_seen_at.desc(), sessions_dsl::id))
+            .select(Session::as_select())
+            .load(conn)
+    })
+    .await
+    .map_err(|e| {
+        error!("error retrieving sessions: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        error!("error retrieving sessions: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// `404` for someone else's session, same as a missing one
+pub async fn revoke_session(
+    conn: Connection,
+    user_id: String,
+    session_id: String,
+) -> Result<(), AppError> {
+    let deleted = conn
+        .interact(|conn| {
+            diesel::delete(
+                sessions_dsl::sessions
+                    .filter(sessions_dsl::id.eq(session_id))
+                    .filter(sessions_dsl::user_id.eq(user_id)),
+            )
+            .execute(conn)
+        })
+        .await
+        .map_err(|e| {
+            error!("error revoking session: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            error!("error revoking session: {:?}", e);
+            AppError::DBError
+        })?;
+    if deleted == 0 {
+        Err(AppError::NotFound)
+    } else {
+        Ok(())
+    }
+}
+
+// logs the user out everywhere, the calling session included
+pub async fn revoke_user_sessions(conn: Connection, user_id: String) -> Result<usize, AppError> {
+    conn.interact(|conn| {
+        diesel::delete(sessions_dsl::sessions.filter(sessions_dsl::user_id.eq(user_id)))
+            .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        error!("error revoking sessions: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        error!("error revoking sessions: {:?}", e);
+        AppError::DBError
+    })
+}
+
 #[cfg(test)]
 pub(crate) async fn delete_user_sessions(
     conn: Connection,
@@ -109,14 +260,25 @@ pub async fn session_from_claims(conn: Connection, claims: Claims) -> Result<Ses
 
     let resp: Option<Session> = conn
         .interact(|conn| {
-            sessions
+            let now = Utc::now().naive_utc();
+            let sess = sessions
                 .inner_join(users_dsl::users)
                 .filter(users_dsl::confirmed.eq(true))
                 .filter(id.eq(claims.jti))
                 .filter(user_id.eq(claims.sub))
                 .select(Session::as_select())
                 .first(conn)
-                .optional()
+                .optional()?;
+            match sess {
+                Some(s) if !session_expired(&s) && s.last_seen_at < now - Duration::minutes(1) => {
+                    diesel::update(sessions.find(s.id))
+                        .set(last_seen_at.eq(now))
+                        .returning(Session::as_returning())
+                        .get_result(conn)
+                        .map(Some)
+                }
+                s => Ok(s),
+            }
         })
         .await
         .map_err(|e| {
@@ -165,3 +327,22 @@ pub(crate) async fn get_session(
         AppError::DBError
     })
 }
+
+#[cfg(test)]
+pub(crate) async fn expire_session(conn: Connection, session_id: String) -> Result<(), AppError> {
+    conn.interact(|conn| {
+        diesel::update(sessions_dsl::sessions.filter(sessions_dsl::id.eq(session_id)))
+            .set(sessions_dsl::expires.eq(Utc::now().naive_utc() - Duration::minutes(1)))
+            .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        error!("error expiring session: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        error!("error expiring session: {:?}", e);
+        AppError::DBError
+    })?;
+    Ok(())
+}
@@ -1,40 +1,223 @@
 use deadpool_diesel::postgres::{Connection, Pool};
-use diesel::prelude::*;
+use diesel::{
+    dsl::{exists, not, now, sql, IntervalDsl},
+    pg::Pg,
+    prelude::*,
+    sql_types::{Array, Bool, Float, Text},
+    Connection as _,
+};
+
+use std::collections::{HashMap, HashSet};
 
 use crate::{
-    db::util::{err_is_not_found, get_conn},
+    db::util::{err_is_not_found, err_is_unique_violation, escape_like, get_conn},
+    metadata::{MetadataFetcher, PageMetadata},
     models::{
-        tab::{NewTab, Tab, TabWithTags},
-        tag::Tag,
+        tab::{
+            BatchTabOp, BatchTabOutcome, BatchTabResult, BatchTabsRequest, BatchTabsResponse,
+            NewTab, NewTabRecord, NewTabTag, NewTabWithTags, Tab, TabChangeset, TabStatus,
+            TabWithTags,
+        },
+        tag::{MaybeNewTag, NewTag, Tag},
     },
     schema::{
         tabs::{self, dsl as tabs_dsl},
-        tabs_tags::dsl as tt_dsl,
-        tags::dsl as tags_dsl,
+        tabs_tags::{self, dsl as tt_dsl},
+        tags::{self, dsl as tags_dsl},
+    },
+    types::{
+        AppError, Cursor, CursorKey, PaginatedResult, PaginationRequest, SortDirection, TabFilter,
+        TabSort, TagMatchMode,
     },
-    types::{AppError, PaginatedResult, PaginationRequest},
+    urls::normalize_url,
 };
 
-pub async fn new_tab(conn: Connection, data: NewTab) -> Result<Tab, AppError> {
-    conn.interact(|conn| {
-        diesel::insert_into(tabs::table)
-            .values(data)
-            .returning(Tab::as_returning())
-            .get_result(conn)
+pub async fn new_tab(
+    pool: Pool,
+    fetcher: &dyn MetadataFetcher,
+    data: NewTab,
+) -> Result<Tab, AppError> {
+    let uid = data.user_id.clone();
+    let normalized = normalize_url(&data.url);
+    let conn = get_conn(pool.clone()).await?;
+    if let Some(id) = find_duplicate(conn, uid.clone(), normalized.clone()).await? {
+        return Err(AppError::Conflict(id));
+    }
+    // fetch before checking out a connection, this can take a while
+    let metadata = fetcher.fetch(&data.url).await.unwrap_or_else(|e| {
+        tracing::warn!("error fetching metadata for {:?}: {:?}", &data.url, e);
+        PageMetadata::default()
+    });
+    let record = NewTabRecord::new(data, metadata);
+    let conn = get_conn(pool.clone()).await?;
+    let inserted = conn
+        .interact(|conn| {
+            diesel::insert_into(tabs::table)
+                .values(record)
+                .on_conflict_do_nothing()
+                .returning(Tab::as_returning())
+                .get_result(conn)
+                .optional()
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("error creating tab: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error creating tab: {:?}", e);
+            AppError::DBError
+        })?;
+    match inserted {
+        Some(tab) => Ok(tab),
+        // the same url was inserted since the check above
+        None => {
+            let conn = get_conn(pool).await?;
+            match find_duplicate(conn, uid, normalized).await? {
+                Some(id) => Err(AppError::Conflict(id)),
+                None => Err(AppError::DBError),
+            }
+        }
+    }
+}
+
+// Creates the tab and attaches its tags in one transaction. Tags with an id have to be the
+// user's; tags without one are matched by name, and only created if the user has no such tag.
+pub async fn new_tab_with_tags(
+    pool: Pool,
+    fetcher: &dyn MetadataFetcher,
+    user_id: String,
+    NewTabWithTags { tab, tags }: NewTabWithTags,
+) -> Result<TabWithTags, AppError> {
+    let normalized = normalize_url(&tab.url);
+    let conn = get_conn(pool.clone()).await?;
+    if let Some(id) = find_duplicate(conn, user_id.clone(), normalized.clone()).await? {
+        return Err(AppError::Conflict(id));
+    }
+    let metadata = fetcher.fetch(&tab.url).await.unwrap_or_else(|e| {
+        tracing::warn!("error fetching metadata for {:?}: {:?}", &tab.url, e);
+        PageMetadata::default()
+    });
+    let record = NewTabRecord::new(
+        NewTab {
+            user_id: user_id.clone(),
+            ..tab
+        },
+        metadata,
+    );
+    let mut tag_ids: HashSet<String> = HashSet::new();
+    let mut tag_names: HashSet<String> = HashSet::new();
+    for MaybeNewTag { id, tag, .. } in tags {
+        match id {
+            Some(id) => tag_ids.insert(id),
+            None => tag_names.insert(tag),
+        };
+    }
+    let conn = get_conn(pool).await?;
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let tag_ids: Vec<String> = tag_ids.into_iter().collect();
+            let owned: i64 = tags_dsl::tags
+                .filter(tags_dsl::id.eq_any(&tag_ids))
+                .filter(tags_dsl::user_id.eq(&user_id))
+                .filter(tags_dsl::deleted_at.is_null())
+                .count()
+                .get_result(conn)?;
+            if owned as usize != tag_ids.len() {
+                return Ok(Err(AppError::WrongCredentials));
+            }
+            let tag_names: Vec<String> = tag_names.into_iter().collect();
+            let new_tags: Vec<NewTag> = tag_names
+                .iter()
+                .map(|tag| NewTag {
+                    user_id: user_id.clone(),
+                    tag: tag.clone(),
+                })
+                .collect();
+            // names the user already has a tag for are skipped here and looked up below
+            diesel::insert_into(tags::table)
+                .values(new_tags)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            let named: Vec<String> = tags_dsl::tags
+                .filter(tags_dsl::user_id.eq(&user_id))
+                .filter(tags_dsl::tag.eq_any(&tag_names))
+                .filter(tags_dsl::deleted_at.is_null())
+                .select(tags_dsl::id)
+                .load(conn)?;
+            let Some(tab) = diesel::insert_into(tabs::table)
+                .values(record)
+                .on_conflict_do_nothing()
+                .returning(Tab::as_returning())
+                .get_result(conn)
+                .optional()?
+            else {
+                // the same url was inserted since the check above
+                return match duplicate_tab_id(conn, &user_id, &normalized, None)? {
+                    Some(id) => Ok(Err(AppError::Conflict(id))),
+                    None => Ok(Err(AppError::DBError)),
+                };
+            };
+            let to_insert: Vec<NewTabTag> = tag_ids
+                .into_iter()
+                .chain(named)
+                .map(|tag_id| NewTabTag {
+                    tab_id: tab.id.clone(),
+                    tag_id,
+                })
+                .collect();
+            diesel::insert_into(tabs_tags::table)
+                .values(to_insert)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            let tags = tags_for_tab(conn, &user_id, &tab.id)?;
+            Ok(Ok(TabWithTags { tab, tags }))
+        })
     })
     .await
     .map_err(|e| {
-        tracing::error!("error creating tab: {:?}", e);
+        tracing::error!("error creating tab with tags: {:?}", e);
         AppError::DBError
     })?
-    .map_err(|e| {
-        if err_is_not_found(&e) {
-            AppError::NotFound
-        } else {
-            tracing::error!("error creating tab: {:?}", e);
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error creating tab with tags: {:?}", e);
+        AppError::DBError
+    })?
+}
+
+async fn find_duplicate(
+    conn: Connection,
+    user_id: String,
+    normalized_url: String,
+) -> Result<Option<String>, AppError> {
+    conn.interact(move |conn| duplicate_tab_id(conn, &user_id, &normalized_url, None))
+        .await
+        .map_err(|e| {
+            tracing::error!("error checking for duplicate tab: {:?}", e);
             AppError::DBError
-        }
-    })
+        })?
+        .map_err(|e| {
+            tracing::error!("error checking for duplicate tab: {:?}", e);
+            AppError::DBError
+        })
+}
+
+pub(crate) fn duplicate_tab_id(
+    conn: &mut PgConnection,
+    user_id: &str,
+    normalized_url: &str,
+    exclude_tab_id: Option<&str>,
+) -> QueryResult<Option<String>> {
+    let mut q = tabs_dsl::tabs
+        .filter(tabs_dsl::user_id.eq(user_id))
+        .filter(tabs_dsl::normalized_url.eq(normalized_url))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .select(tabs_dsl::id)
+        .into_boxed();
+    if let Some(tab_id) = exclude_tab_id {
+        q = q.filter(tabs_dsl::id.ne(tab_id));
+    }
+    q.first(conn).optional()
 }
 
 pub async fn get_tab(conn: Connection, user_id: String, tab_id: String) -> Result<Tab, AppError> {
@@ -42,6 +225,7 @@ pub async fn get_tab(conn: Connection, user_id: String, tab_id: String) -> Resul
         tabs_dsl::tabs
             .filter(tabs_dsl::id.eq(tab_id))
             .filter(tabs_dsl::user_id.eq(user_id))
+            .filter(tabs_dsl::deleted_at.is_null())
             .select(Tab::as_select())
             .first(conn)
     })
@@ -60,6 +244,292 @@ pub async fn get_tab(conn: Connection, user_id: String, tab_id: String) -> Resul
     })
 }
 
+// A new url gets its page's metadata fetched, as for `new_tab`, in place of the old page's
+pub async fn update_tab(
+    pool: Pool,
+    fetcher: &dyn MetadataFetcher,
+    user_id: String,
+    tab_id: String,
+    mut changes: TabChangeset,
+) -> Result<Tab, AppError> {
+    if let Some(url) = changes.url.clone() {
+        let conn = get_conn(pool.clone()).await?;
+        let current = get_tab(conn, user_id.clone(), tab_id.clone()).await?;
+        if current.url != url {
+            let metadata = fetcher.fetch(&url).await.unwrap_or_else(|e| {
+                tracing::warn!("error fetching metadata for {:?}: {:?}", &url, e);
+                PageMetadata::default()
+            });
+            changes = changes.with_metadata(metadata);
+        }
+    }
+    let conn = get_conn(pool).await?;
+    let normalized = changes.normalized_url.clone();
+    let (uid, tid) = (user_id.clone(), tab_id.clone());
+    conn.interact(move |conn| {
+        if let Some(Some(normalized)) = &changes.normalized_url {
+            if let Some(id) = duplicate_tab_id(conn, &user_id, normalized, Some(&tab_id))? {
+                return Ok(Err(id));
+            }
+        }
+        diesel::update(
+            tabs_dsl::tabs
+                .filter(tabs_dsl::id.eq(tab_id))
+                .filter(tabs_dsl::user_id.eq(user_id))
+                .filter(tabs_dsl::deleted_at.is_null()),
+        )
+        .set(changes)
+        .returning(Tab::as_returning())
+        .get_result(conn)
+        .map(Ok)
+        .or_else(|e| {
+            // the same url was saved since the check above
+            if err_is_unique_violation(&e, "tabs_user_id_normalized_url") {
+                if let Some(Some(normalized)) = &normalized {
+                    if let Some(id) = duplicate_tab_id(conn, &uid, normalized, Some(&tid))? {
+                        return Ok(Err(id));
+                    }
+                }
+            }
+            Err(e)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error updating tab: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error updating tab: {:?}", e);
+            AppError::DBError
+        }
+    })?
+    .map_err(AppError::Conflict)
+}
+
+pub async fn set_tab_status(
+    conn: Connection,
+    user_id: String,
+    tab_id: String,
+    status: TabStatus,
+) -> Result<Tab, AppError> {
+    conn.interact(move |conn| {
+        diesel::update(
+            tabs_dsl::tabs
+                .filter(tabs_dsl::id.eq(tab_id))
+                .filter(tabs_dsl::user_id.eq(user_id))
+                .filter(tabs_dsl::deleted_at.is_null()),
+        )
+        .set(tabs_dsl::status.eq(status))
+        .returning(Tab::as_returning())
+        .get_result(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error setting tab status: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error setting tab status: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+// Applies `op` to every one of the user's live tabs in `tab_ids` in a single transaction.
+// Other ids are reported as not found; every tag has to be the user's or nothing is done.
+pub async fn batch_update_tabs(
+    conn: Connection,
+    user_id: String,
+    BatchTabsRequest { tab_ids, op }: BatchTabsRequest,
+) -> Result<BatchTabsResponse, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let mut seen = HashSet::new();
+            let tab_ids: Vec<String> = tab_ids
+                .into_iter()
+                .filter(|id| seen.insert(id.clone()))
+                .collect();
+            let owned: HashSet<String> = tabs_dsl::tabs
+                .filter(tabs_dsl::id.eq_any(&tab_ids))
+                .filter(tabs_dsl::user_id.eq(&user_id))
+                .filter(tabs_dsl::deleted_at.is_null())
+                .select(tabs_dsl::id)
+                .for_update()
+                .load::<String>(conn)?
+                .into_iter()
+                .collect();
+            let targets: Vec<&String> = tab_ids.iter().filter(|id| owned.contains(*id)).collect();
+            let updated: HashSet<String> = match op {
+                BatchTabOp::AddTags { tag_ids } | BatchTabOp::RemoveTags { tag_ids }
+                    if !user_owns_tags(conn, &user_id, &tag_ids)? =>
+                {
+                    return Ok(Err(AppError::WrongCredentials));
+                }
+                _ if targets.is_empty() => HashSet::new(),
+                BatchTabOp::AddTags { tag_ids } => {
+                    let to_insert: Vec<NewTabTag> = targets
+                        .iter()
+                        .flat_map(|tab_id| {
+                            tag_ids.iter().map(|tag_id| NewTabTag {
+                                tab_id: tab_id.to_string(),
+                                tag_id: tag_id.clone(),
+                            })
+                        })
+                        .collect();
+                    diesel::insert_into(tabs_tags::table)
+                        .values(to_insert)
+                        .on_conflict_do_nothing()
+                        .returning(tt_dsl::tab_id)
+                        .get_results::<String>(conn)?
+                        .into_iter()
+                        .collect()
+                }
+                BatchTabOp::RemoveTags { tag_ids } => diesel::delete(
+                    tt_dsl::tabs_tags
+                        .filter(tt_dsl::tab_id.eq_any(&targets))
+                        .filter(tt_dsl::tag_id.eq_any(tag_ids)),
+                )
+                .returning(tt_dsl::tab_id)
+                .get_results::<String>(conn)?
+                .into_iter()
+                .collect(),
+                BatchTabOp::Delete => {
+                    diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq_any(&targets)))
+                        .set(tabs_dsl::deleted_at.eq(now))
+                        .returning(tabs_dsl::id)
+                        .get_results::<String>(conn)?
+                        .into_iter()
+                        .collect()
+                }
+                BatchTabOp::SetStatus { status } => diesel::update(
+                    tabs_dsl::tabs
+                        .filter(tabs_dsl::id.eq_any(&targets))
+                        .filter(tabs_dsl::status.ne(status)),
+                )
+                .set(tabs_dsl::status.eq(status))
+                .returning(tabs_dsl::id)
+                .get_results::<String>(conn)?
+                .into_iter()
+                .collect(),
+            };
+            let results = tab_ids
+                .into_iter()
+                .map(|tab_id| {
+                    let outcome = if updated.contains(&tab_id) {
+                        BatchTabOutcome::Updated
+                    } else if owned.contains(&tab_id) {
+                        BatchTabOutcome::Unchanged
+                    } else {
+                        BatchTabOutcome::NotFound
+                    };
+                    BatchTabResult { tab_id, outcome }
+                })
+                .collect();
+            Ok(Ok(BatchTabsResponse { results }))
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error updating tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error updating tabs: {:?}", e);
+        AppError::DBError
+    })?
+}
+
+fn user_owns_tags(conn: &mut PgConnection, user_id: &str, tag_ids: &[String]) -> QueryResult<bool> {
+    let distinct: HashSet<&String> = tag_ids.iter().collect();
+    let owned: i64 = tags_dsl::tags
+        .filter(tags_dsl::id.eq_any(tag_ids))
+        .filter(tags_dsl::user_id.eq(user_id))
+        .filter(tags_dsl::deleted_at.is_null())
+        .count()
+        .get_result(conn)?;
+    Ok(owned as usize == distinct.len())
+}
+
+// Folds `source_id` into `target_id`: notes are concatenated, a missing title is filled in,
+// tags are unioned and the source tab is moved to the trash.
+pub async fn merge_tabs(
+    conn: Connection,
+    user_id: String,
+    target_id: String,
+    source_id: String,
+) -> Result<TabWithTags, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let user_tab = |conn: &mut PgConnection, tab_id: &str| {
+                tabs_dsl::tabs
+                    .filter(tabs_dsl::id.eq(tab_id.to_string()))
+                    .filter(tabs_dsl::user_id.eq(user_id.clone()))
+                    .filter(tabs_dsl::deleted_at.is_null())
+                    .select(Tab::as_select())
+                    .first(conn)
+            };
+            let target = user_tab(conn, &target_id)?;
+            let source = user_tab(conn, &source_id)?;
+            let tab = fold_tab(conn, target, source)?;
+            let tags = tags_for_tab(conn, &user_id, &tab.id)?;
+            Ok(TabWithTags { tab, tags })
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error merging tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error merging tabs: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+// `source`'s notes, title and tags go to `target`, then `source` goes to the trash
+fn fold_tab(conn: &mut PgConnection, target: Tab, source: Tab) -> QueryResult<Tab> {
+    let notes = match (target.notes, source.notes) {
+        (Some(t), Some(s)) if t != s => Some(format!("{}\n\n{}", t, s)),
+        (t, s) => t.or(s),
+    };
+    let title = target.title.or(source.title);
+    let source_tag_ids: Vec<String> = tt_dsl::tabs_tags
+        .filter(tt_dsl::tab_id.eq(source.id.clone()))
+        .select(tt_dsl::tag_id)
+        .get_results(conn)?;
+    let to_insert: Vec<NewTabTag> = source_tag_ids
+        .into_iter()
+        .map(|tag_id| NewTabTag {
+            tab_id: target.id.clone(),
+            tag_id,
+        })
+        .collect();
+    if !to_insert.is_empty() {
+        diesel::insert_into(tabs_tags::table)
+            .values(to_insert)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(source.id)))
+        .set(tabs_dsl::deleted_at.eq(now))
+        .execute(conn)?;
+    diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(target.id)))
+        .set((tabs_dsl::notes.eq(notes), tabs_dsl::title.eq(title)))
+        .returning(Tab::as_returning())
+        .get_result(conn)
+}
+
 pub async fn get_tab_with_tags(
     pool: Pool,
     user_id: String,
@@ -71,14 +541,7 @@ pub async fn get_tab_with_tags(
     let tab = get_tab(c, user_id.clone(), tab_id.clone()).await?;
     let c = get_conn(pool.clone()).await?;
     let tags = c
-        .interact(move |conn| {
-            tags_dsl::tags
-                .inner_join(tt_dsl::tabs_tags.on(tt_dsl::tag_id.eq(tags_dsl::id)))
-                .filter(tt_dsl::tab_id.eq(tid))
-                .filter(tags_dsl::user_id.eq(uid))
-                .select(Tag::as_select())
-                .get_results(conn)
-        })
+        .interact(move |conn| tags_for_tab(conn, &uid, &tid))
         .await
         .map_err(|e| {
             tracing::error!("error getting tab tags: {:?}", e);
@@ -91,16 +554,31 @@ pub async fn get_tab_with_tags(
     Ok(TabWithTags { tab, tags })
 }
 
+fn tags_for_tab(conn: &mut PgConnection, user_id: &str, tab_id: &str) -> QueryResult<Vec<Tag>> {
+    tags_dsl::tags
+        .inner_join(tt_dsl::tabs_tags.on(tt_dsl::tag_id.eq(tags_dsl::id)))
+        .filter(tt_dsl::tab_id.eq(tab_id))
+        .filter(tags_dsl::user_id.eq(user_id))
+        .filter(tags_dsl::deleted_at.is_null())
+        .order(tags_dsl::tag.asc())
+        .select(Tag::as_select())
+        .get_results(conn)
+}
+
 pub async fn get_user_tabs(
     pool: Pool,
     user_id: String,
+    filter: TabFilter,
     pr: PaginationRequest,
 ) -> Result<PaginatedResult<Tab>, AppError> {
+    if pr.cursor.is_some() {
+        return get_user_tabs_keyset(pool, user_id, filter, pr).await;
+    }
     let offset = pr.offset();
     let limit = pr.limit();
 
     let c = get_conn(pool).await?;
-    let count_q = tabs_dsl::tabs.filter(tabs_dsl::user_id.eq(user_id.clone()));
+    let count_q = user_tabs_query(user_id.clone(), &filter);
     let count: i64 = c
         .interact(|conn| count_q.count().get_result(conn))
         .await
@@ -114,9 +592,7 @@ pub async fn get_user_tabs(
         })?;
     let has_more = count - offset > limit;
 
-    let tabs_q = tabs_dsl::tabs
-        .filter(tabs_dsl::user_id.eq(user_id.clone()))
-        .order(tabs_dsl::created_at.desc())
+    let tabs_q = order_user_tabs(user_tabs_query(user_id, &filter), &filter)
         .limit(limit)
         .offset(offset);
     let tabs = c
@@ -133,21 +609,327 @@ pub async fn get_user_tabs(
     Ok(PaginatedResult {
         results: tabs,
         has_more,
+        next_cursor: None,
+    })
+}
+
+// with the default (newest first) order, tabs created while paging show up before the first
+// page rather than shifting the later ones
+async fn get_user_tabs_keyset(
+    pool: Pool,
+    user_id: String,
+    filter: TabFilter,
+    pr: PaginationRequest,
+) -> Result<PaginatedResult<Tab>, AppError> {
+    let limit = pr.limit();
+    let mut tabs_q = user_tabs_query(user_id, &filter);
+    if let Some(cursor) = pr.decode_cursor()? {
+        tabs_q = after_cursor(tabs_q, &filter, cursor)?;
+    }
+    let tabs_q = order_user_tabs(tabs_q, &filter).limit(limit + 1);
+    let c = get_conn(pool).await?;
+    let tabs = c
+        .interact(move |conn| tabs_q.select(Tab::as_select()).get_results(conn))
+        .await
+        .map_err(|e| {
+            tracing::error!("error getting user tabs: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error getting user tabs: {:?}", e);
+            AppError::DBError
+        })?;
+    let sort = filter.sort;
+    Ok(PaginatedResult::from_keyset(tabs, limit, |t: &Tab| {
+        Cursor {
+            key: match sort {
+                TabSort::CreatedAt => CursorKey::CreatedAt(t.created_at),
+                TabSort::ModifiedAt => CursorKey::ModifiedAt(t.modified_at),
+                TabSort::Url => CursorKey::Url(t.url.clone()),
+            },
+            id: t.id.clone(),
+        }
+    }))
+}
+
+fn user_tabs_query(user_id: String, filter: &TabFilter) -> tabs::BoxedQuery<'static, Pg> {
+    let mut q = tabs_dsl::tabs
+        .filter(tabs_dsl::user_id.eq(user_id))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .into_boxed();
+    if let Some(after) = filter.created_after {
+        q = q.filter(tabs_dsl::created_at.ge(after));
+    }
+    if let Some(before) = filter.created_before {
+        q = q.filter(tabs_dsl::created_at.lt(before));
+    }
+    if let Some(statuses) = &filter.statuses {
+        q = q.filter(tabs_dsl::status.eq_any(statuses.clone()));
+    }
+    if let Some(domain) = &filter.domain {
+        q = q.filter(
+            tabs_dsl::domain
+                .eq(domain.clone())
+                .or(tabs_dsl::domain.like(format!("%.{}", escape_like(domain)))),
+        );
+    }
+    if filter.tag_ids.is_empty() {
+        return q;
+    }
+    // a tag matches tabs tagged with any of its descendants too
+    let tagged = |tag_ids: Vec<String>| {
+        sql::<Bool>(
+            "tabs.id IN (SELECT tab_id FROM tabs_tags WHERE tag_id IN ( \
+                WITH RECURSIVE subtree (id) AS ( \
+                    SELECT id FROM tags WHERE id = ANY(",
+        )
+        .bind::<Array<Text>, _>(tag_ids)
+        .sql(
+            ") AND deleted_at IS NULL \
+                    UNION \
+                    SELECT c.id FROM tags c JOIN subtree s ON c.parent_id = s.id \
+                    WHERE c.deleted_at IS NULL \
+                ) SELECT id FROM subtree))",
+        )
+    };
+    match filter.tag_mode {
+        TagMatchMode::Any => {
+            q = q.filter(tagged(filter.tag_ids.clone()));
+        }
+        TagMatchMode::All => {
+            for tag_id in filter.tag_ids.iter() {
+                q = q.filter(tagged(vec![tag_id.clone()]));
+            }
+        }
+    }
+    q
+}
+
+// trashed tags don't count towards tag filters
+fn live_tag_ids() -> diesel::dsl::Select<
+    diesel::dsl::Filter<tags_dsl::tags, diesel::dsl::IsNull<tags_dsl::deleted_at>>,
+    tags_dsl::id,
+> {
+    tags_dsl::tags
+        .filter(tags_dsl::deleted_at.is_null())
+        .select(tags_dsl::id)
+}
+
+// `id` breaks ties so pages don't overlap
+fn order_user_tabs(
+    q: tabs::BoxedQuery<'static, Pg>,
+    filter: &TabFilter,
+) -> tabs::BoxedQuery<'static, Pg> {
+    use SortDirection::*;
+    match (filter.sort, filter.direction) {
+        (TabSort::CreatedAt, Asc) => q.order((tabs_dsl::created_at.asc(), tabs_dsl::id.asc())),
+        (TabSort::CreatedAt, Desc) => q.order((tabs_dsl::created_at.desc(), tabs_dsl::id.desc())),
+        (TabSort::ModifiedAt, Asc) => q.order((tabs_dsl::modified_at.asc(), tabs_dsl::id.asc())),
+        (TabSort::ModifiedAt, Desc) => q.order((tabs_dsl::modified_at.desc(), tabs_dsl::id.desc())),
+        (TabSort::Url, Asc) => q.order((tabs_dsl::url.asc(), tabs_dsl::id.asc())),
+        (TabSort::Url, Desc) => q.order((tabs_dsl::url.desc(), tabs_dsl::id.desc())),
+    }
+}
+
+// rows strictly after `cursor` in `order_user_tabs` order
+fn after_cursor(
+    q: tabs::BoxedQuery<'static, Pg>,
+    filter: &TabFilter,
+    Cursor { key, id }: Cursor,
+) -> Result<tabs::BoxedQuery<'static, Pg>, AppError> {
+    use SortDirection::*;
+    let q = match (key, filter.sort, filter.direction) {
+        (CursorKey::CreatedAt(v), TabSort::CreatedAt, Asc) => q.filter(
+            tabs_dsl::created_at
+                .gt(v)
+                .or(tabs_dsl::created_at.eq(v).and(tabs_dsl::id.gt(id))),
+        ),
+        (CursorKey::CreatedAt(v), TabSort::CreatedAt, Desc) => q.filter(
+            tabs_dsl::created_at
+                .lt(v)
+                .or(tabs_dsl::created_at.eq(v).and(tabs_dsl::id.lt(id))),
+        ),
+        (CursorKey::ModifiedAt(v), TabSort::ModifiedAt, Asc) => q.filter(
+            tabs_dsl::modified_at
+                .gt(v)
+                .or(tabs_dsl::modified_at.eq(v).and(tabs_dsl::id.gt(id))),
+        ),
+        (CursorKey::ModifiedAt(v), TabSort::ModifiedAt, Desc) => q.filter(
+            tabs_dsl::modified_at
+                .lt(v)
+                .or(tabs_dsl::modified_at.eq(v).and(tabs_dsl::id.lt(id))),
+        ),
+        (CursorKey::Url(v), TabSort::Url, Asc) => q.filter(
+            tabs_dsl::url
+                .gt(v.clone())
+                .or(tabs_dsl::url.eq(v).and(tabs_dsl::id.gt(id))),
+        ),
+        (CursorKey::Url(v), TabSort::Url, Desc) => q.filter(
+            tabs_dsl::url
+                .lt(v.clone())
+                .or(tabs_dsl::url.eq(v).and(tabs_dsl::id.lt(id))),
+        ),
+        // the cursor came from a listing with a different sort
+        _ => return Err(AppError::BadRequest),
+    };
+    Ok(q)
+}
+
+// which of a user's tabs an export batch comes from
+pub enum TabSet {
+    All,
+    Tagged(String),
+    Untagged,
+}
+
+// oldest first, for exports
+pub async fn get_tabs_with_tags_batch(
+    pool: Pool,
+    user_id: String,
+    set: &TabSet,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<TabWithTags>, AppError> {
+    let filter = TabFilter {
+        sort: TabSort::CreatedAt,
+        direction: SortDirection::Asc,
+        ..Default::default()
+    };
+    let mut tabs_q = user_tabs_query(user_id.clone(), &filter);
+    match set {
+        TabSet::All => {}
+        // only the tag itself, each descendant gets its own folder
+        TabSet::Tagged(tag_id) => {
+            tabs_q = tabs_q.filter(exists(
+                tt_dsl::tabs_tags
+                    .filter(tt_dsl::tab_id.eq(tabs_dsl::id))
+                    .filter(tt_dsl::tag_id.eq(tag_id.clone())),
+            ));
+        }
+        TabSet::Untagged => {
+            tabs_q = tabs_q.filter(not(exists(
+                tt_dsl::tabs_tags
+                    .filter(tt_dsl::tab_id.eq(tabs_dsl::id))
+                    .filter(tt_dsl::tag_id.eq_any(live_tag_ids())),
+            )));
+        }
+    }
+    if let Some(cursor) = after {
+        tabs_q = after_cursor(tabs_q, &filter, cursor)?;
+    }
+    let tabs_q = order_user_tabs(tabs_q, &filter).limit(limit);
+    let c = get_conn(pool).await?;
+    c.interact(move |conn| {
+        let tabs: Vec<Tab> = tabs_q.select(Tab::as_select()).get_results(conn)?;
+        let tab_ids: Vec<String> = tabs.iter().map(|t| t.id.clone()).collect();
+        let mut tags_by_tab: HashMap<String, Vec<Tag>> = HashMap::new();
+        let tab_tags: Vec<(String, Tag)> = tt_dsl::tabs_tags
+            .inner_join(tags_dsl::tags.on(tags_dsl::id.eq(tt_dsl::tag_id)))
+            .filter(tt_dsl::tab_id.eq_any(tab_ids))
+            .filter(tags_dsl::user_id.eq(user_id))
+            .filter(tags_dsl::deleted_at.is_null())
+            .order(tags_dsl::tag.asc())
+            .select((tt_dsl::tab_id, Tag::as_select()))
+            .get_results(conn)?;
+        for (tab_id, tag) in tab_tags {
+            tags_by_tab.entry(tab_id).or_default().push(tag);
+        }
+        Ok(tabs
+            .into_iter()
+            .map(|tab| {
+                let tags = tags_by_tab.remove(&tab.id).unwrap_or_default();
+                TabWithTags { tab, tags }
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error getting tabs with tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error getting tabs with tags: {:?}", e);
+        AppError::DBError
+    })
+}
+
+pub async fn search_user_tabs(
+    pool: Pool,
+    user_id: String,
+    q: String,
+    pr: PaginationRequest,
+) -> Result<PaginatedResult<Tab>, AppError> {
+    let offset = pr.offset();
+    let limit = pr.limit();
+    // see the `search` column in the 2026-10-18-141500_tab-search migration
+    let matches = |q: String| {
+        sql::<Bool>("search @@ websearch_to_tsquery('english', ")
+            .bind::<Text, _>(q)
+            .sql(")")
+    };
+    let rank = |q: String| {
+        sql::<Float>("ts_rank(search, websearch_to_tsquery('english', ")
+            .bind::<Text, _>(q)
+            .sql("))")
+    };
+
+    let c = get_conn(pool).await?;
+    let count_q = tabs_dsl::tabs
+        .filter(tabs_dsl::user_id.eq(user_id.clone()))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .filter(matches(q.clone()));
+    let count: i64 = c
+        .interact(|conn| count_q.count().get_result(conn))
+        .await
+        .map_err(|e| {
+            tracing::error!("error getting tab search count: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error getting tab search count: {:?}", e);
+            AppError::DBError
+        })?;
+    let has_more = count - offset > limit;
+
+    let tabs_q = tabs_dsl::tabs
+        .filter(tabs_dsl::user_id.eq(user_id))
+        .filter(tabs_dsl::deleted_at.is_null())
+        .filter(matches(q.clone()))
+        .order((rank(q).desc(), tabs_dsl::created_at.desc()))
+        .limit(limit)
+        .offset(offset);
+    let tabs = c
+        .interact(move |conn| tabs_q.select(Tab::as_select()).get_results(conn))
+        .await
+        .map_err(|e| {
+            tracing::error!("error searching user tabs: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error searching user tabs: {:?}", e);
+            AppError::DBError
+        })?;
+    Ok(PaginatedResult {
+        results: tabs,
+        has_more,
+        next_cursor: None,
     })
 }
 
-#[allow(dead_code)]
+// moves the tab to the trash, its tags stay attached in case it's restored
 pub async fn delete_tab(
     conn: Connection,
     user_id: String,
     tab_id: String,
 ) -> Result<usize, AppError> {
     conn.interact(|conn| {
-        diesel::delete(
+        diesel::update(
             tabs_dsl::tabs
                 .filter(tabs_dsl::id.eq(tab_id))
-                .filter(tabs_dsl::user_id.eq(user_id)),
+                .filter(tabs_dsl::user_id.eq(user_id))
+                .filter(tabs_dsl::deleted_at.is_null()),
         )
+        .set(tabs_dsl::deleted_at.eq(now))
         .execute(conn)
     })
     .await
@@ -165,6 +947,114 @@ pub async fn delete_tab(
     })
 }
 
+pub async fn delete_tabs(
+    conn: Connection,
+    user_id: String,
+    tab_ids: Vec<String>,
+) -> Result<usize, AppError> {
+    conn.interact(|conn| {
+        diesel::update(
+            tabs_dsl::tabs
+                .filter(tabs_dsl::id.eq_any(tab_ids))
+                .filter(tabs_dsl::user_id.eq(user_id))
+                .filter(tabs_dsl::deleted_at.is_null()),
+        )
+        .set(tabs_dsl::deleted_at.eq(now))
+        .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error deleting tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error deleting tabs: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// `409` if a tab with the same url was saved in the meantime
+pub async fn restore_tab(
+    conn: Connection,
+    user_id: String,
+    tab_id: String,
+) -> Result<Tab, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let tab: Tab = tabs_dsl::tabs
+                .filter(tabs_dsl::id.eq(&tab_id))
+                .filter(tabs_dsl::user_id.eq(&user_id))
+                .filter(tabs_dsl::deleted_at.is_not_null())
+                .select(Tab::as_select())
+                .first(conn)?;
+            let normalized = normalize_url(&tab.url);
+            if let Some(id) = duplicate_tab_id(conn, &user_id, &normalized, None)? {
+                return Ok(Err(id));
+            }
+            diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(tab.id)))
+                .set(tabs_dsl::deleted_at.eq(None::<chrono::NaiveDateTime>))
+                .returning(Tab::as_returning())
+                .get_result(conn)
+                .map(Ok)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error restoring tab: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error restoring tab: {:?}", e);
+            AppError::DBError
+        }
+    })?
+    .map_err(AppError::Conflict)
+}
+
+// most recently deleted first
+pub async fn get_trashed_tabs(conn: Connection, user_id: String) -> Result<Vec<Tab>, AppError> {
+    conn.interact(move |conn| {
+        tabs_dsl::tabs
+            .filter(tabs_dsl::user_id.eq(user_id))
+            .filter(tabs_dsl::deleted_at.is_not_null())
+            .order((tabs_dsl::deleted_at.desc(), tabs_dsl::id.asc()))
+            .select(Tab::as_select())
+            .get_results(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error getting trashed tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error getting trashed tabs: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// permanently deletes every tab that's been in the trash longer than `retention_days`
+pub async fn purge_tabs(conn: Connection, retention_days: i32) -> Result<usize, AppError> {
+    conn.interact(move |conn| {
+        diesel::delete(
+            tabs_dsl::tabs
+                .filter(tabs_dsl::deleted_at.lt((now - retention_days.days()).nullable())),
+        )
+        .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error purging tabs: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error purging tabs: {:?}", e);
+        AppError::DBError
+    })
+}
+
 #[cfg(test)]
 pub async fn delete_user_tabs(conn: Connection, user_id: String) -> Result<usize, AppError> {
     conn.interact(|conn| {
@@ -183,6 +1073,10 @@ pub async fn delete_user_tabs(conn: Connection, user_id: String) -> Result<usize
 
 #[cfg(test)] // for now
 pub async fn bulk_insert_tabs(conn: Connection, data: Vec<NewTab>) -> Result<Vec<Tab>, AppError> {
+    let data: Vec<NewTabRecord> = data
+        .into_iter()
+        .map(|t| NewTabRecord::new(t, PageMetadata::default()))
+        .collect();
     conn.interact(|conn| {
         diesel::insert_into(tabs::table)
             .values(data)
@@ -1,22 +1,27 @@
 use deadpool_diesel::postgres::{Connection, Pool};
-use diesel::dsl::exists;
+use diesel::dsl::{exists, now, sql, IntervalDsl};
+use diesel::expression::SqlLiteral;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Float, Text};
+use diesel::Connection as _;
 
+#[cfg(test)]
+use crate::models::tab::TabTag;
 use crate::{
-    db::util::{err_is_not_found, get_conn},
+    db::util::{err_is_not_found, escape_like, get_conn},
     models::{
         tab::{
-            AttachTagRequest, CreatedTabTag, DetachTagRequest, NewTabTag, TabTag,
-            TagAttachedResponse, TagDetachedResponse,
+            AttachTagRequest, CreatedTabTag, DetachTagRequest, NewTabTag, TagAttachedResponse,
+            TagDetachedResponse,
         },
-        tag::{NewTag, Tag},
+        tag::{NewTag, Tag, TagChangeset, TagWithCount},
     },
     schema::{
         tabs::dsl as tabs_dsl,
         tabs_tags::{self, dsl as tt_dsl},
         tags::{self, dsl as tags_dsl},
     },
-    types::{AppError, PaginatedResult, PaginationRequest},
+    types::{AppError, Cursor, CursorKey, PaginatedResult, PaginationRequest, TagSort},
 };
 
 pub async fn new_tag(conn: Connection, data: NewTag) -> Result<Tag, AppError> {
@@ -41,17 +46,20 @@ pub async fn new_tag(conn: Connection, data: NewTag) -> Result<Tag, AppError> {
     })
 }
 
+// moves the tag to the trash, it stays attached to its tabs in case it's restored
 pub async fn delete_tag(
     conn: Connection,
     user_id: String,
     tag_id: String,
 ) -> Result<usize, AppError> {
     conn.interact(|conn| {
-        diesel::delete(
+        diesel::update(
             tags_dsl::tags
                 .filter(tags_dsl::id.eq(tag_id))
-                .filter(tags_dsl::user_id.eq(user_id)),
+                .filter(tags_dsl::user_id.eq(user_id))
+                .filter(tags_dsl::deleted_at.is_null()),
         )
+        .set(tags_dsl::deleted_at.eq(now))
         .execute(conn)
     })
     .await
@@ -158,11 +166,41 @@ pub async fn detach_tag(
     }
 }
 
+// live tabs with the tag
+fn tab_count() -> SqlLiteral<BigInt> {
+    sql::<BigInt>(
+        "(SELECT count(*) FROM tabs_tags JOIN tabs ON tabs.id = tabs_tags.tab_id \
+         WHERE tabs_tags.tag_id = tags.id AND tabs.deleted_at IS NULL)",
+    )
+}
+
+// no live tabs and no live child tags
+fn unused() -> SqlLiteral<Bool> {
+    sql::<Bool>(
+        "NOT EXISTS (SELECT 1 FROM tabs_tags JOIN tabs ON tabs.id = tabs_tags.tab_id \
+         WHERE tabs_tags.tag_id = tags.id AND tabs.deleted_at IS NULL) \
+         AND NOT EXISTS (SELECT 1 FROM tags children \
+         WHERE children.parent_id = tags.id AND children.deleted_at IS NULL)",
+    )
+}
+
+fn with_count((tag, tab_count): (Tag, i64)) -> TagWithCount {
+    TagWithCount { tag, tab_count }
+}
+
 pub async fn get_user_tags(
     conn: Connection,
     user_id: String,
     pr: PaginationRequest,
-) -> Result<PaginatedResult<Tag>, AppError> {
+    sort: TagSort,
+) -> Result<PaginatedResult<TagWithCount>, AppError> {
+    if pr.cursor.is_some() {
+        // usage changes under the cursor, so it can't be paged by keyset
+        if sort != TagSort::Tag {
+            return Err(AppError::BadRequest);
+        }
+        return get_user_tags_keyset(conn, user_id, pr).await;
+    }
     let offset = pr.offset();
     let limit = pr.limit();
     let uid = user_id.clone();
@@ -172,6 +210,7 @@ pub async fn get_user_tags(
         .interact(move |conn| {
             tags_dsl::tags
                 .filter(tags_dsl::user_id.eq(cuid))
+                .filter(tags_dsl::deleted_at.is_null())
                 .count()
                 .get_result(conn)
         })
@@ -186,13 +225,24 @@ pub async fn get_user_tags(
         })?;
     let has_more = count - offset > limit;
     let tuid = uid.clone();
-    let tags = conn
+    let tags_q = tags_dsl::tags
+        .filter(tags_dsl::user_id.eq(tuid))
+        .filter(tags_dsl::deleted_at.is_null())
+        .into_boxed();
+    let tags_q = match sort {
+        TagSort::Tag => tags_q.order((tags_dsl::pinned.desc(), tags_dsl::tag.desc())),
+        TagSort::Usage => tags_q.order((
+            tags_dsl::pinned.desc(),
+            tab_count().desc(),
+            tags_dsl::tag.asc(),
+        )),
+    };
+    let tags: Vec<(Tag, i64)> = conn
         .interact(move |conn| {
-            tags_dsl::tags
-                .filter(tags_dsl::user_id.eq(tuid))
-                .order(tags_dsl::tag.desc())
+            tags_q
                 .limit(limit)
                 .offset(offset)
+                .select((Tag::as_select(), tab_count()))
                 .get_results(conn)
         })
         .await
@@ -205,22 +255,171 @@ pub async fn get_user_tags(
             AppError::DBError
         })?;
     Ok(PaginatedResult {
-        results: tags,
+        results: tags.into_iter().map(with_count).collect(),
         has_more,
+        next_cursor: None,
+    })
+}
+
+// pinned first, then oldest first, so tags created while paging end up at the end of their part
+async fn get_user_tags_keyset(
+    conn: Connection,
+    user_id: String,
+    pr: PaginationRequest,
+) -> Result<PaginatedResult<TagWithCount>, AppError> {
+    let limit = pr.limit();
+    let mut tags_q = tags_dsl::tags
+        .filter(tags_dsl::user_id.eq(user_id))
+        .filter(tags_dsl::deleted_at.is_null())
+        .into_boxed();
+    if let Some(cursor) = pr.decode_cursor()? {
+        let (CursorKey::Pinned(pinned, created_at), id) = (cursor.key, cursor.id) else {
+            return Err(AppError::BadRequest);
+        };
+        let after = tags_dsl::pinned.eq(pinned).and(
+            tags_dsl::created_at
+                .gt(created_at)
+                .or(tags_dsl::created_at.eq(created_at).and(tags_dsl::id.gt(id))),
+        );
+        tags_q = if pinned {
+            tags_q.filter(tags_dsl::pinned.eq(false).or(after))
+        } else {
+            tags_q.filter(after)
+        };
+    }
+    let tags_q = tags_q
+        .order((
+            tags_dsl::pinned.desc(),
+            tags_dsl::created_at.asc(),
+            tags_dsl::id.asc(),
+        ))
+        .limit(limit + 1);
+    let rows: Vec<(Tag, i64, chrono::NaiveDateTime)> = conn
+        .interact(move |conn| {
+            tags_q
+                .select((Tag::as_select(), tab_count(), tags_dsl::created_at))
+                .get_results(conn)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("error getting user tags: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error getting user tags: {:?}", e);
+            AppError::DBError
+        })?;
+    let page = PaginatedResult::from_keyset(rows, limit, |(tag, _, created_at)| Cursor {
+        key: CursorKey::Pinned(tag.pinned, *created_at),
+        id: tag.id.clone(),
+    });
+    Ok(PaginatedResult {
+        results: page
+            .results
+            .into_iter()
+            .map(|(tag, tab_count, _)| TagWithCount { tag, tab_count })
+            .collect(),
+        has_more: page.has_more,
+        next_cursor: page.next_cursor,
+    })
+}
+
+// alphabetical
+pub async fn get_unused_tags(conn: Connection, user_id: String) -> Result<Vec<Tag>, AppError> {
+    conn.interact(move |conn| {
+        tags_dsl::tags
+            .filter(tags_dsl::user_id.eq(user_id))
+            .filter(tags_dsl::deleted_at.is_null())
+            .filter(unused())
+            .order(tags_dsl::tag.asc())
+            .select(Tag::as_select())
+            .get_results(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error getting unused tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error getting unused tags: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// moves them to the trash, like deleting them one by one would
+pub async fn delete_unused_tags(conn: Connection, user_id: String) -> Result<usize, AppError> {
+    conn.interact(move |conn| {
+        diesel::update(
+            tags_dsl::tags
+                .filter(tags_dsl::user_id.eq(user_id))
+                .filter(tags_dsl::deleted_at.is_null())
+                .filter(unused()),
+        )
+        .set(tags_dsl::deleted_at.eq(now))
+        .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error deleting unused tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error deleting unused tags: {:?}", e);
+        AppError::DBError
     })
 }
 
+// unpaginated, alphabetical
+pub async fn get_all_user_tags(conn: Connection, user_id: String) -> Result<Vec<Tag>, AppError> {
+    conn.interact(move |conn| {
+        tags_dsl::tags
+            .filter(tags_dsl::user_id.eq(user_id))
+            .filter(tags_dsl::deleted_at.is_null())
+            .order(tags_dsl::tag.asc())
+            .select(Tag::as_select())
+            .get_results(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error getting user tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error getting user tags: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// Tags starting with the fragment come first, then ones containing it or close to one of
+// their words (per `pg_trgm`, so typos still match), each by similarity and then name.
 pub async fn get_user_tags_fuzzy(
     conn: Connection,
     user_id: String,
     to_match: String,
+    limit: i64,
 ) -> Result<Vec<Tag>, AppError> {
+    let escaped = escape_like(&to_match);
+    let prefix = format!("{}%", escaped);
+    let contains = format!("%{}%", escaped);
     conn.interact(move |conn| {
         tags_dsl::tags
             .filter(tags_dsl::user_id.eq(user_id))
-            .filter(tags_dsl::tag.ilike(format!("%{}%", to_match)))
-            .order(tags_dsl::tag.asc())
-            .limit(10)
+            .filter(tags_dsl::deleted_at.is_null())
+            .filter(
+                tags_dsl::tag.ilike(contains).or(sql::<Bool>("")
+                    .bind::<Text, _>(to_match.clone())
+                    .sql(" <% tags.tag")),
+            )
+            .order((
+                tags_dsl::tag.ilike(prefix).desc(),
+                sql::<Float>("word_similarity(")
+                    .bind::<Text, _>(to_match)
+                    .sql(", tags.tag)")
+                    .desc(),
+                tags_dsl::tag.asc(),
+            ))
+            .limit(limit)
+            .select(Tag::as_select())
             .get_results(conn)
     })
     .await
@@ -238,7 +437,8 @@ async fn tab_belongs(conn: Connection, tab_id: String, user_id: String) -> Resul
         diesel::select(exists(
             tabs_dsl::tabs
                 .filter(tabs_dsl::id.eq(tab_id))
-                .filter(tabs_dsl::user_id.eq(user_id)),
+                .filter(tabs_dsl::user_id.eq(user_id))
+                .filter(tabs_dsl::deleted_at.is_null()),
         ))
         .get_result(conn)
     })
@@ -258,7 +458,8 @@ async fn tag_belongs(conn: Connection, tag_id: String, user_id: String) -> Resul
         diesel::select(exists(
             tags_dsl::tags
                 .filter(tags_dsl::id.eq(tag_id))
-                .filter(tags_dsl::user_id.eq(user_id)),
+                .filter(tags_dsl::user_id.eq(user_id))
+                .filter(tags_dsl::deleted_at.is_null()),
         ))
         .get_result(conn)
     })
@@ -273,6 +474,279 @@ async fn tag_belongs(conn: Connection, tag_id: String, user_id: String) -> Resul
     })
 }
 
+// `409` with the other tag's id if the user already has a tag with the new name
+pub async fn update_tag(
+    conn: Connection,
+    user_id: String,
+    tag_id: String,
+    changes: TagChangeset,
+) -> Result<Tag, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            if let Some(tag) = &changes.tag {
+                let existing: Option<String> = tags_dsl::tags
+                    .filter(tags_dsl::user_id.eq(&user_id))
+                    .filter(tags_dsl::tag.eq(tag))
+                    .filter(tags_dsl::id.ne(&tag_id))
+                    .filter(tags_dsl::deleted_at.is_null())
+                    .select(tags_dsl::id)
+                    .first(conn)
+                    .optional()?;
+                if let Some(id) = existing {
+                    return Ok(Err(id));
+                }
+            }
+            diesel::update(
+                tags_dsl::tags
+                    .filter(tags_dsl::id.eq(&tag_id))
+                    .filter(tags_dsl::user_id.eq(&user_id))
+                    .filter(tags_dsl::deleted_at.is_null()),
+            )
+            .set(&changes)
+            .returning(Tag::as_returning())
+            .get_result(conn)
+            .map(Ok)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error updating tag: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error updating tag: {:?}", e);
+            AppError::DBError
+        }
+    })?
+    .map_err(AppError::Conflict)
+}
+
+// `400` if `parent_id` is the tag itself or one of its descendants
+pub async fn set_tag_parent(
+    conn: Connection,
+    user_id: String,
+    tag_id: String,
+    parent_id: Option<String>,
+) -> Result<Tag, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let user_tag = |conn: &mut PgConnection, tag_id: &str| {
+                tags_dsl::tags
+                    .filter(tags_dsl::id.eq(tag_id.to_string()))
+                    .filter(tags_dsl::user_id.eq(user_id.clone()))
+                    .filter(tags_dsl::deleted_at.is_null())
+                    .select(Tag::as_select())
+                    .first(conn)
+            };
+            let tag = user_tag(conn, &tag_id)?;
+            if let Some(parent_id) = &parent_id {
+                let parent = user_tag(conn, parent_id)?;
+                if ancestor_ids(conn, &parent.id)?.contains(&tag.id) {
+                    return Ok(Err(AppError::BadRequest));
+                }
+            }
+            diesel::update(tags_dsl::tags.filter(tags_dsl::id.eq(tag.id)))
+                .set(tags_dsl::parent_id.eq(parent_id))
+                .returning(Tag::as_returning())
+                .get_result(conn)
+                .map(Ok)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error setting tag parent: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error setting tag parent: {:?}", e);
+            AppError::DBError
+        }
+    })?
+}
+
+#[derive(QueryableByName)]
+struct TagId {
+    #[diesel(sql_type = Text)]
+    id: String,
+}
+
+// the tag itself, its parent, its parent's parent and so on
+fn ancestor_ids(conn: &mut PgConnection, tag_id: &str) -> QueryResult<Vec<String>> {
+    diesel::sql_query(
+        "WITH RECURSIVE ancestors (id, parent_id) AS ( \
+            SELECT id, parent_id FROM tags WHERE id = $1 \
+            UNION \
+            SELECT t.id, t.parent_id FROM tags t JOIN ancestors a ON t.id = a.parent_id \
+        ) SELECT id FROM ancestors",
+    )
+    .bind::<Text, _>(tag_id)
+    .load::<TagId>(conn)
+    .map(|ids| ids.into_iter().map(|t| t.id).collect())
+}
+
+// Tags every tab tagged `source_id` with `target_id` too, then moves the source tag to the trash,
+// still attached to its tabs in case it's restored. The source's children become the target's.
+pub async fn merge_tags(
+    conn: Connection,
+    user_id: String,
+    target_id: String,
+    source_id: String,
+) -> Result<Tag, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let user_tag = |conn: &mut PgConnection, tag_id: &str| {
+                tags_dsl::tags
+                    .filter(tags_dsl::id.eq(tag_id.to_string()))
+                    .filter(tags_dsl::user_id.eq(user_id.clone()))
+                    .filter(tags_dsl::deleted_at.is_null())
+                    .select(Tag::as_select())
+                    .first(conn)
+            };
+            let target = user_tag(conn, &target_id)?;
+            let source = user_tag(conn, &source_id)?;
+            let tab_ids: Vec<String> = tt_dsl::tabs_tags
+                .filter(tt_dsl::tag_id.eq(&source.id))
+                .select(tt_dsl::tab_id)
+                .get_results(conn)?;
+            let to_insert: Vec<NewTabTag> = tab_ids
+                .into_iter()
+                .map(|tab_id| NewTabTag {
+                    tab_id,
+                    tag_id: target.id.clone(),
+                })
+                .collect();
+            // tabs that already had both tags keep a single row
+            diesel::insert_into(tabs_tags::table)
+                .values(to_insert)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            // a target nested under the source moves up first, or it would become its own ancestor
+            let target = if ancestor_ids(conn, &target.id)?.contains(&source.id) {
+                diesel::update(tags_dsl::tags.filter(tags_dsl::id.eq(&target.id)))
+                    .set(tags_dsl::parent_id.eq(&source.parent_id))
+                    .returning(Tag::as_returning())
+                    .get_result(conn)?
+            } else {
+                target
+            };
+            diesel::update(tags_dsl::tags.filter(tags_dsl::parent_id.eq(&source.id)))
+                .set(tags_dsl::parent_id.eq(&target.id))
+                .execute(conn)?;
+            diesel::update(tags_dsl::tags.filter(tags_dsl::id.eq(&source.id)))
+                .set(tags_dsl::deleted_at.eq(now))
+                .execute(conn)?;
+            Ok(target)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error merging tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error merging tags: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+// `409` if a tag with the same name was created in the meantime
+pub async fn restore_tag(
+    conn: Connection,
+    user_id: String,
+    tag_id: String,
+) -> Result<Tag, AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            let tag: Tag = tags_dsl::tags
+                .filter(tags_dsl::id.eq(&tag_id))
+                .filter(tags_dsl::user_id.eq(&user_id))
+                .filter(tags_dsl::deleted_at.is_not_null())
+                .select(Tag::as_select())
+                .first(conn)?;
+            let existing: Option<String> = tags_dsl::tags
+                .filter(tags_dsl::user_id.eq(&user_id))
+                .filter(tags_dsl::tag.eq(&tag.tag))
+                .filter(tags_dsl::deleted_at.is_null())
+                .select(tags_dsl::id)
+                .first(conn)
+                .optional()?;
+            if let Some(id) = existing {
+                return Ok(Err(id));
+            }
+            diesel::update(tags_dsl::tags.filter(tags_dsl::id.eq(tag.id)))
+                .set(tags_dsl::deleted_at.eq(None::<chrono::NaiveDateTime>))
+                .returning(Tag::as_returning())
+                .get_result(conn)
+                .map(Ok)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error restoring tag: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error restoring tag: {:?}", e);
+            AppError::DBError
+        }
+    })?
+    .map_err(AppError::Conflict)
+}
+
+// most recently deleted first
+pub async fn get_trashed_tags(conn: Connection, user_id: String) -> Result<Vec<Tag>, AppError> {
+    conn.interact(move |conn| {
+        tags_dsl::tags
+            .filter(tags_dsl::user_id.eq(user_id))
+            .filter(tags_dsl::deleted_at.is_not_null())
+            .order((tags_dsl::deleted_at.desc(), tags_dsl::id.asc()))
+            .select(Tag::as_select())
+            .get_results(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error getting trashed tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error getting trashed tags: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// permanently deletes every tag that's been in the trash longer than `retention_days`
+pub async fn purge_tags(conn: Connection, retention_days: i32) -> Result<usize, AppError> {
+    conn.interact(move |conn| {
+        diesel::delete(
+            tags_dsl::tags
+                .filter(tags_dsl::deleted_at.lt((now - retention_days.days()).nullable())),
+        )
+        .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error purging tags: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e| {
+        tracing::error!("error purging tags: {:?}", e);
+        AppError::DBError
+    })
+}
+
 #[cfg(test)]
 pub async fn delete_user_tags(conn: Connection, user_id: String) -> Result<usize, AppError> {
     conn.interact(|conn| {
@@ -294,6 +768,8 @@ pub async fn get_tag(conn: Connection, tag_id: String) -> Result<Tag, AppError>
     conn.interact(move |conn| {
         tags_dsl::tags
             .filter(tags_dsl::id.eq(tag_id))
+            .filter(tags_dsl::deleted_at.is_null())
+            .select(Tag::as_select())
             .get_result(conn)
     })
     .await
@@ -339,6 +815,7 @@ pub async fn mk_tab_tag(
     })
 }
 
+#[cfg(test)]
 pub async fn bulk_mk_tab_tags(
     conn: Connection,
     data: Vec<NewTabTag>,
@@ -385,20 +862,25 @@ pub async fn get_tab_tag(
         }
     })
 }
+// the import inserts its tags with `insert_tags`, in the same transaction as its tabs
+#[cfg(test)]
 pub async fn bulk_insert_tags(conn: Connection, data: Vec<NewTag>) -> Result<Vec<Tag>, AppError> {
-    conn.interact(|conn| {
-        diesel::insert_into(tags::table)
-            .values(data)
-            .returning(Tag::as_returning())
-            .get_results(conn)
-    })
-    .await
-    .map_err(|e| {
-        tracing::error!("error bulk inserting tags: {:?}", e);
-        AppError::DBError
-    })?
-    .map_err(|e| {
-        tracing::error!("error bulk inserting tags: {:?}", e);
-        AppError::DBError
-    })
+    conn.interact(move |conn| insert_tags(conn, &data))
+        .await
+        .map_err(|e| {
+            tracing::error!("error bulk inserting tags: {:?}", e);
+            AppError::DBError
+        })?
+        .map_err(|e| {
+            tracing::error!("error bulk inserting tags: {:?}", e);
+            AppError::DBError
+        })
+}
+
+// for use inside a transaction
+pub(crate) fn insert_tags(conn: &mut PgConnection, data: &[NewTag]) -> QueryResult<Vec<Tag>> {
+    diesel::insert_into(tags::table)
+        .values(data)
+        .returning(Tag::as_returning())
+        .get_results(conn)
 }
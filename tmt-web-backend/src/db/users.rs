@@ -4,19 +4,32 @@ use crate::models::user::{DeconfirmedUser, NewConfirmedUser};
 use crate::{
     models::{
         invite::{CreatedInvite, Invite, InviteStatus, NewInvite},
+        password_reset::{NewPasswordReset, PasswordReset},
         user::{CreatedUser, NewUser, User},
     },
+    passwords::PasswordHasher,
     schema::invites,
     schema::invites::dsl as invites_dsl,
+    schema::password_resets::{self, dsl as resets_dsl},
+    schema::sessions::dsl as sessions_dsl,
     schema::users,
     schema::users::dsl as users_dsl,
+    tokens::{hash_secret, new_secret},
     types::AppError,
 };
 use chrono::Utc;
 use deadpool_diesel::postgres::{Connection, Pool};
-use diesel::prelude::*;
+use diesel::{prelude::*, Connection as _};
 
-pub async fn new_user(conn: Connection, user: NewUser) -> Result<CreatedUser, AppError> {
+pub async fn new_user(
+    conn: Connection,
+    hasher: &PasswordHasher,
+    user: NewUser,
+) -> Result<CreatedUser, AppError> {
+    let user = NewUser {
+        password: hasher.hash(user.password).await?,
+        ..user
+    };
     conn.interact(|conn| {
         diesel::insert_into(users::table)
             .values(user)
@@ -39,6 +52,10 @@ pub async fn new_user_confirmed(
     conn: Connection,
     cu_data: NewConfirmedUser,
 ) -> Result<User, AppError> {
+    let cu_data = NewConfirmedUser {
+        password: PasswordHasher::for_tests().hash(cu_data.password).await?,
+        ..cu_data
+    };
     conn.interact(|conn| {
         diesel::insert_into(users::table)
             .values(cu_data)
@@ -185,6 +202,123 @@ pub async fn get_invite(conn: Connection, invite_id: String) -> Result<Invite, A
     })
 }
 
+// Replaces any earlier reset the user asked for, `None` if no confirmed user has the email. The
+// code to mail out comes back with the reset, only its hash is stored
+pub async fn new_password_reset(
+    conn: Connection,
+    email: String,
+) -> Result<Option<(PasswordReset, String)>, AppError> {
+    let code = new_secret();
+    let token_hash = hash_secret(&code);
+    conn.interact(|conn| {
+        conn.transaction(|conn| {
+            let user_id: Option<String> = users_dsl::users
+                .filter(users_dsl::email.eq(email))
+                .filter(users_dsl::confirmed.eq(true))
+                .select(users_dsl::id)
+                .first(conn)
+                .optional()?;
+            let Some(user_id) = user_id else {
+                return Ok(None);
+            };
+            diesel::delete(resets_dsl::password_resets.filter(resets_dsl::user_id.eq(&user_id)))
+                .execute(conn)?;
+            diesel::insert_into(password_resets::table)
+                .values(NewPasswordReset {
+                    user_id,
+                    token_hash,
+                })
+                .returning(PasswordReset::as_returning())
+                .get_result(conn)
+                .map(|reset| Some((reset, code)))
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error creating password reset: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error creating password reset: {:?}", e);
+        AppError::DBError
+    })
+}
+
+// Uses up the reset and logs the user out everywhere. `404` if the token is unknown, used or
+// expired
+pub async fn reset_password(
+    conn: Connection,
+    token: String,
+    password_hash: String,
+) -> Result<String, AppError> {
+    let token_hash = hash_secret(&token);
+    conn.interact(|conn| {
+        let now = Utc::now().naive_utc();
+        conn.transaction(|conn| {
+            let user_id: String = diesel::delete(
+                resets_dsl::password_resets
+                    .filter(resets_dsl::token_hash.eq(token_hash))
+                    .filter(resets_dsl::expires.gt(now)),
+            )
+            .returning(resets_dsl::user_id)
+            .get_result(conn)?;
+            diesel::update(users_dsl::users.filter(users_dsl::id.eq(&user_id)))
+                .set(users_dsl::password.eq(password_hash))
+                .execute(conn)?;
+            diesel::delete(sessions_dsl::sessions.filter(sessions_dsl::user_id.eq(&user_id)))
+                .execute(conn)?;
+            Ok(user_id)
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error resetting password: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            tracing::error!("error resetting password: {:?}", e);
+            AppError::DBError
+        }
+    })
+}
+
+// Logs the user out everywhere but `keep_session_id`, and drops any pending password reset
+pub async fn change_password(
+    conn: Connection,
+    user_id: String,
+    password_hash: String,
+    keep_session_id: String,
+) -> Result<(), AppError> {
+    conn.interact(move |conn| {
+        conn.transaction(|conn| {
+            diesel::update(users_dsl::users.filter(users_dsl::id.eq(&user_id)))
+                .set(users_dsl::password.eq(password_hash))
+                .execute(conn)?;
+            diesel::delete(
+                sessions_dsl::sessions
+                    .filter(sessions_dsl::user_id.eq(&user_id))
+                    .filter(sessions_dsl::id.ne(keep_session_id)),
+            )
+            .execute(conn)?;
+            diesel::delete(resets_dsl::password_resets.filter(resets_dsl::user_id.eq(&user_id)))
+                .execute(conn)?;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error changing password: {:?}", e);
+        AppError::DBError
+    })?
+    .map_err(|e: diesel::result::Error| {
+        tracing::error!("error changing password: {:?}", e);
+        AppError::DBError
+    })
+}
+
 #[cfg(test)]
 pub async fn deconfirm_user(
     conn: Connection,
@@ -213,8 +347,34 @@ pub async fn deconfirm_user(
     })
 }
 
-// cfg(test) until it becomes useful elsewhere
+// an md5-crypt hash, as passwords were stored before argon2
 #[cfg(test)]
+pub async fn set_legacy_password(
+    conn: Connection,
+    user_id: String,
+    password: String,
+) -> Result<usize, AppError> {
+    use diesel::{dsl::sql, sql_types::Text};
+    conn.interact(|conn| {
+        diesel::update(users_dsl::users.filter(users_dsl::id.eq(user_id)))
+            .set(
+                users_dsl::password.eq(sql::<Text>("crypt(")
+                    .bind::<Text, _>(password)
+                    .sql(", gen_salt('md5'))")),
+            )
+            .execute(conn)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("error setting password: {:?}", e);
+        AppError::DBErrorWithMessage(e.to_string())
+    })?
+    .map_err(|e| {
+        tracing::error!("error setting password: {:?}", e);
+        AppError::DBErrorWithMessage(e.to_string())
+    })
+}
+
 pub async fn get_user(conn: Connection, user_id: String) -> Result<User, AppError> {
     conn.interact(|conn| {
         users_dsl::users
@@ -228,7 +388,11 @@ pub async fn get_user(conn: Connection, user_id: String) -> Result<User, AppErro
     })?
     .map_err(|e| {
         tracing::error!("error retrieving user: {:?}", e);
-        AppError::DBErrorWithMessage(e.to_string())
+        if err_is_not_found(&e) {
+            AppError::NotFound
+        } else {
+            AppError::DBErrorWithMessage(e.to_string())
+        }
     })
 }
 
@@ -1,6 +1,6 @@
 use crate::types::AppError;
 use deadpool_diesel::postgres::{Connection, Pool};
-use diesel::result::{Error as DE, UnexpectedNullError};
+use diesel::result::{DatabaseErrorKind, Error as DE};
 
 pub(crate) async fn get_conn(pool: Pool) -> Result<Connection, AppError> {
     pool.get().await.map_err(|e| {
@@ -12,11 +12,18 @@ pub fn err_is_not_found(err: &DE) -> bool {
     matches!(err, DE::NotFound)
 }
 
-pub fn err_is_deserialization_unexpected_null(err: &DE) -> bool {
-    match err {
-        DE::DeserializationError(e) => e.downcast_ref::<UnexpectedNullError>().is_some(),
-        _ => false,
-    }
+pub(crate) fn err_is_unique_violation(err: &DE, constraint: &str) -> bool {
+    matches!(
+        err,
+        DE::DatabaseError(DatabaseErrorKind::UniqueViolation, info)
+            if info.constraint_name() == Some(constraint)
+    )
+}
+
+pub(crate) fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 #[cfg(test)]
@@ -41,7 +48,10 @@ pub mod test_util {
             t.user_id.clone_from(&user_id);
             tabs.push(t);
         }
-        tabs::bulk_insert_tabs(conn, tabs).await
+        let mut tabs = tabs::bulk_insert_tabs(conn, tabs).await?;
+        // the order `get_user_tabs` lists them in, they all share a `created_at`
+        tabs.sort_by(|a, b| (&b.created_at, &b.id).cmp(&(&a.created_at, &a.id)));
+        Ok(tabs)
     }
     pub async fn bulk_create_tags(
         conn: Connection,
@@ -0,0 +1,201 @@
+use deadpool_diesel::postgres::Pool;
+use tokio::sync::mpsc;
+
+use crate::{
+    bookmarks::{self, Bookmark},
+    db::{
+        tabs::{self, TabSet},
+        tags,
+    },
+    models::tab::{ExportFormat, TabWithTags},
+    types::{AppError, AppState, Cursor, CursorKey},
+};
+
+const BATCH_SIZE: i64 = 500;
+
+const CSV_HEADER: &str =
+    "id,url,title,notes,description,favicon_url,canonical_url,status,created_at,modified_at,tags\r\n";
+
+pub type ExportSender = mpsc::Sender<Result<String, AppError>>;
+
+// Writes every one of the user's tabs to `tx` a batch at a time. Errors part way through
+// are sent along, which aborts the response.
+pub async fn write_export(st: AppState, user_id: String, format: ExportFormat, tx: ExportSender) {
+    if let Err(e) = write(st, user_id, format, &tx).await {
+        // a closed channel just means the client went away
+        if !tx.is_closed() {
+            tracing::error!("error exporting tabs: {:?}", e);
+            let _ = tx.send(Err(e)).await;
+        }
+    }
+}
+
+async fn write(
+    st: AppState,
+    user_id: String,
+    format: ExportFormat,
+    tx: &ExportSender,
+) -> Result<(), AppError> {
+    match format {
+        ExportFormat::Json => {
+            send(tx, "[".into()).await?;
+            let mut batches = Batches::new(st.pool(), user_id, TabSet::All);
+            let mut first = true;
+            while let Some(batch) = batches.next().await? {
+                let mut chunk = String::new();
+                for tab in batch {
+                    if !first {
+                        chunk.push(',');
+                    }
+                    first = false;
+                    chunk.push_str(&serde_json::to_string(&tab).map_err(|e| {
+                        tracing::error!("error serializing tab: {:?}", e);
+                        AppError::InternalServerError
+                    })?);
+                }
+                send(tx, chunk).await?;
+            }
+            send(tx, "]".into()).await
+        }
+        ExportFormat::Csv => {
+            send(tx, CSV_HEADER.into()).await?;
+            let mut batches = Batches::new(st.pool(), user_id, TabSet::All);
+            while let Some(batch) = batches.next().await? {
+                send(tx, batch.iter().map(csv_row).collect()).await?;
+            }
+            Ok(())
+        }
+        ExportFormat::Html => {
+            send(tx, bookmarks::HEADER.into()).await?;
+            // each tab goes in the folder of its first tag (alphabetically), the rest of its tags
+            // are in its `TAGS`, so it's only written once
+            let user_tags = tags::get_all_user_tags(st.conn().await?, user_id.clone()).await?;
+            for tag in user_tags {
+                send(tx, bookmarks::render_folder_start(&tag.tag)).await?;
+                let mut batches =
+                    Batches::new(st.pool(), user_id.clone(), TabSet::Tagged(tag.id.clone()));
+                while let Some(batch) = batches.next().await? {
+                    let chunk: String = batch
+                        .iter()
+                        .filter(|t| t.tags.first().is_some_and(|first| first.id == tag.id))
+                        .map(|t| html_link(t, 2))
+                        .collect();
+                    if !chunk.is_empty() {
+                        send(tx, chunk).await?;
+                    }
+                }
+                send(tx, bookmarks::render_folder_end().into()).await?;
+            }
+            let mut batches = Batches::new(st.pool(), user_id, TabSet::Untagged);
+            while let Some(batch) = batches.next().await? {
+                send(tx, batch.iter().map(|t| html_link(t, 1)).collect()).await?;
+            }
+            send(tx, bookmarks::FOOTER.into()).await
+        }
+    }
+}
+
+async fn send(tx: &ExportSender, chunk: String) -> Result<(), AppError> {
+    tx.send(Ok(chunk))
+        .await
+        .map_err(|_| AppError::InternalServerError)
+}
+
+struct Batches {
+    pool: Pool,
+    user_id: String,
+    set: TabSet,
+    after: Option<Cursor>,
+    done: bool,
+}
+
+impl Batches {
+    fn new(pool: Pool, user_id: String, set: TabSet) -> Self {
+        Self {
+            pool,
+            user_id,
+            set,
+            after: None,
+            done: false,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<Vec<TabWithTags>>, AppError> {
+        if self.done {
+            return Ok(None);
+        }
+        let batch = tabs::get_tabs_with_tags_batch(
+            self.pool.clone(),
+            self.user_id.clone(),
+            &self.set,
+            self.after.take(),
+            BATCH_SIZE,
+        )
+        .await?;
+        self.done = (batch.len() as i64) < BATCH_SIZE;
+        self.after = batch.last().map(|t| Cursor {
+            key: CursorKey::CreatedAt(t.tab.created_at),
+            id: t.tab.id.clone(),
+        });
+        Ok(Some(batch).filter(|b| !b.is_empty()))
+    }
+}
+
+fn csv_row(TabWithTags { tab, tags }: &TabWithTags) -> String {
+    let timestamp = |ts: &chrono::NaiveDateTime| ts.format("%Y-%m-%dT%H:%M:%S%.f").to_string();
+    let tag_names: Vec<&str> = tags.iter().map(|t| t.tag.as_str()).collect();
+    let fields = [
+        tab.id.clone(),
+        tab.url.clone(),
+        tab.title.clone().unwrap_or_default(),
+        tab.notes.clone().unwrap_or_default(),
+        tab.description.clone().unwrap_or_default(),
+        tab.favicon_url.clone().unwrap_or_default(),
+        tab.canonical_url.clone().unwrap_or_default(),
+        tab.status.as_str().to_string(),
+        timestamp(&tab.created_at),
+        timestamp(&tab.modified_at),
+        tag_names.join(","),
+    ];
+    let mut row = fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn html_link(TabWithTags { tab, tags }: &TabWithTags, indent: usize) -> String {
+    let bookmark = Bookmark {
+        url: tab.url.clone(),
+        title: tab.title.clone(),
+        notes: tab.notes.clone(),
+        add_date: Some(tab.created_at),
+        last_modified: Some(tab.modified_at),
+        folders: Vec::new(),
+        tags: tags.iter().map(|t| t.tag.clone()).collect(),
+    };
+    bookmarks::render_link(&bookmark, indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
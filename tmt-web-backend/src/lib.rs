@@ -1,21 +1,36 @@
 mod auth;
+mod bookmarks;
 mod db;
+mod export;
+mod mailer;
+mod metadata;
 mod models;
+mod passwords;
 mod routes;
 mod schema;
+mod tokens;
+mod trash;
 mod types;
+mod urls;
 use axum::Router;
 use routes::{
-    auth::auth_router, misc::misc_router, tabs::tabs_router, tags::tags_router, users::users_router,
+    auth::auth_router, export::export_router, groups::groups_router, import::import_router,
+    misc::misc_router, tabs::tabs_router, tags::tags_router, trash::trash_router,
+    users::users_router,
 };
+pub use trash::spawn_trash_purge;
 pub use types::AppState;
 
 pub fn make_app(state: AppState) -> Router {
     Router::new()
         .merge(auth_router())
+        .merge(export_router())
+        .merge(groups_router())
+        .merge(import_router())
         .merge(misc_router())
         .merge(tabs_router())
         .merge(tags_router())
+        .merge(trash_router())
         .merge(users_router())
         .with_state(state)
 }
@@ -0,0 +1,61 @@
+use crate::types::AppError;
+use axum::async_trait;
+#[cfg(test)]
+use once_cell::sync::Lazy;
+#[cfg(test)]
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+// There's no mail server to hand off to yet, so mail is only noted in the log. Not the body:
+// it can hold secrets like reset codes
+#[cfg(not(test))]
+pub struct LogMailer;
+
+#[cfg(not(test))]
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!("mail to {}: {}", email.to, email.subject);
+        Ok(())
+    }
+}
+
+// every test server gets its own state, so sent mail is kept process-wide; tests pick theirs
+// out by recipient
+#[cfg(test)]
+static SENT: Lazy<Mutex<Vec<Email>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[cfg(test)]
+pub struct FakeMailer;
+
+#[cfg(test)]
+impl FakeMailer {
+    pub fn sent_to(to: &str) -> Vec<Email> {
+        SENT.lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.to == to)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Mailer for FakeMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        SENT.lock().unwrap().push(email);
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
+use std::net::SocketAddr;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use tmt_web_backend::{make_app, AppState};
+use tmt_web_backend::{make_app, spawn_trash_purge, AppState};
 
 #[tokio::main]
 async fn main() {
@@ -11,8 +12,16 @@ async fn main() {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
-    let app = make_app(AppState::from_env());
+    let state = AppState::from_env();
+    spawn_trash_purge(state.clone());
+    let app = make_app(state);
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await.unwrap();
     tracing::info!("listening on 0.0.0.0:8080");
-    axum::serve(listener, app).await.unwrap();
+    // the peer address is recorded on sessions
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,339 @@
+use axum::async_trait;
+use reqwest::{header, redirect, Client, StatusCode};
+use scraper::{Html, Selector};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use url::Url;
+
+const MAX_REDIRECTS: usize = 5;
+const MAX_BODY_BYTES: usize = 512 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+// for the whole fetch, redirects included; tabs are saved while the client waits
+const FETCH_DEADLINE: Duration = Duration::from_secs(8);
+const USER_AGENT: &str = concat!("tmt-web/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub favicon_url: Option<String>,
+    pub canonical_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    InvalidUrl,
+    ForbiddenAddress,
+    TooManyRedirects,
+    Timeout,
+    BadStatus(StatusCode),
+    Request(reqwest::Error),
+    Resolve(std::io::Error),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Request(e)
+    }
+}
+
+#[async_trait]
+pub trait MetadataFetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> Result<PageMetadata, FetchError>;
+}
+
+pub struct HttpMetadataFetcher {
+    client: Client,
+    // only ever true in development, see `AppState::from_env`
+    allow_private: bool,
+}
+
+impl HttpMetadataFetcher {
+    pub fn new(allow_private: bool) -> Self {
+        let client = Self::client_builder()
+            .build()
+            .expect("error building http client");
+        Self {
+            client,
+            allow_private,
+        }
+    }
+
+    fn client_builder() -> reqwest::ClientBuilder {
+        Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(FETCH_TIMEOUT)
+            // redirects are followed by hand so every hop gets checked
+            .redirect(redirect::Policy::none())
+            // a proxy from the environment would connect wherever it likes, past the pinning
+            .no_proxy()
+    }
+
+    // Resolves `url`'s host and refuses anything that isn't a public address. The returned
+    // client is pinned to the address that was checked, so a second DNS lookup can't be used
+    // to sneak past the check.
+    async fn client_for(&self, url: &Url) -> Result<Client, FetchError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(FetchError::InvalidUrl);
+        }
+        if self.allow_private {
+            return Ok(self.client.clone());
+        }
+        let host = url.host_str().ok_or(FetchError::InvalidUrl)?;
+        let port = url.port_or_known_default().ok_or(FetchError::InvalidUrl)?;
+        let addrs: Vec<SocketAddr> = match url.host() {
+            Some(url::Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+            Some(url::Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+            _ => tokio::net::lookup_host((host, port))
+                .await
+                .map_err(FetchError::Resolve)?
+                .collect(),
+        };
+        if addrs.is_empty() || !addrs.iter().all(|a| is_public_addr(a.ip())) {
+            return Err(FetchError::ForbiddenAddress);
+        }
+        Ok(Self::client_builder().resolve(host, addrs[0]).build()?)
+    }
+}
+
+#[async_trait]
+impl MetadataFetcher for HttpMetadataFetcher {
+    async fn fetch(&self, url: &str) -> Result<PageMetadata, FetchError> {
+        tokio::time::timeout(FETCH_DEADLINE, self.fetch_following_redirects(url))
+            .await
+            .map_err(|_| FetchError::Timeout)?
+    }
+}
+
+impl HttpMetadataFetcher {
+    async fn fetch_following_redirects(&self, url: &str) -> Result<PageMetadata, FetchError> {
+        let mut url = Url::parse(url).map_err(|_| FetchError::InvalidUrl)?;
+        for _ in 0..=MAX_REDIRECTS {
+            let client = self.client_for(&url).await?;
+            let mut resp = client.get(url.clone()).send().await?;
+            if resp.status().is_redirection() {
+                let location = resp
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .ok_or(FetchError::BadStatus(resp.status()))?;
+                url = url.join(location).map_err(|_| FetchError::InvalidUrl)?;
+                continue;
+            }
+            if !resp.status().is_success() {
+                return Err(FetchError::BadStatus(resp.status()));
+            }
+            let is_html = resp
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|ct| ct.to_str().ok())
+                .is_none_or(|ct| ct.contains("html"));
+            if !is_html {
+                return Ok(PageMetadata::default());
+            }
+            let mut body: Vec<u8> = Vec::new();
+            while let Some(chunk) = resp.chunk().await? {
+                body.extend_from_slice(&chunk);
+                if body.len() >= MAX_BODY_BYTES {
+                    body.truncate(MAX_BODY_BYTES);
+                    break;
+                }
+            }
+            return Ok(parse_metadata(&String::from_utf8_lossy(&body), &url));
+        }
+        Err(FetchError::TooManyRedirects)
+    }
+}
+
+#[cfg(test)]
+pub struct FakeMetadataFetcher;
+
+#[cfg(test)]
+#[async_trait]
+impl MetadataFetcher for FakeMetadataFetcher {
+    async fn fetch(&self, url: &str) -> Result<PageMetadata, FetchError> {
+        Ok(PageMetadata {
+            title: Some(format!("Title of {}", url)),
+            description: Some(format!("Description of {}", url)),
+            favicon_url: None,
+            canonical_url: None,
+        })
+    }
+}
+
+pub(crate) fn parse_metadata(html: &str, base: &Url) -> PageMetadata {
+    let doc = Html::parse_document(html);
+    let first_attr = |selector: &str, attr: &str| -> Option<String> {
+        let sel = Selector::parse(selector).expect("invalid selector");
+        doc.select(&sel)
+            .filter_map(|el| el.value().attr(attr))
+            .map(clean_text)
+            .find(|v| !v.is_empty())
+    };
+    let first_text = |selector: &str| -> Option<String> {
+        let sel = Selector::parse(selector).expect("invalid selector");
+        doc.select(&sel)
+            .map(|el| clean_text(&el.text().collect::<String>()))
+            .find(|v| !v.is_empty())
+    };
+    let resolve = |href: String| base.join(&href).ok().map(String::from);
+
+    PageMetadata {
+        title: first_attr(r#"meta[property="og:title"]"#, "content")
+            .or_else(|| first_text("title")),
+        description: first_attr(r#"meta[name="description"]"#, "content")
+            .or_else(|| first_attr(r#"meta[property="og:description"]"#, "content")),
+        favicon_url: first_attr(r#"link[rel~="icon"]"#, "href").and_then(resolve),
+        canonical_url: first_attr(r#"link[rel="canonical"]"#, "href").and_then(resolve),
+    }
+}
+
+fn clean_text(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn is_public_addr(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match embedded_v4(ip) {
+            Some(v4) => is_public_v4(v4),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+// IPv6 forms that reach an IPv4 address, which is what has to be checked: IPv4-mapped and
+// IPv4-compatible (`::a.b.c.d`, which also covers `::` and `::1`), 6to4 and well-known NAT64
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let seg = ip.segments();
+    let v4 = |hi: u16, lo: u16| Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo));
+    match seg {
+        [0, 0, 0, 0, 0, 0 | 0xffff, ..] => ip.to_ipv4(),
+        [0x2002, hi, lo, ..] => Some(v4(hi, lo)),
+        [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] => Some(v4(hi, lo)),
+        _ => None,
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network", shared address space, benchmarking, reserved
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local, link local, documentation
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8)
+        // tunnels to IPv4 that `embedded_v4` can't see through: Teredo, NAT64 with a
+        // network-specific prefix (local-use, or the rest of the well-known one)
+        || (first == 0x2001 && ip.segments()[1] == 0)
+        || (first == 0x64 && ip.segments()[1] == 0xff9b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        let html = r#"<html><head>
+            <title>
+              Plain   title
+            </title>
+            <meta name="description" content="A page">
+            <link rel="shortcut icon" href="/static/favicon.png">
+            <link rel="canonical" href="https://example.com/page">
+            </head><body></body></html>"#;
+        let base = Url::parse("https://example.com/page?utm_source=x").unwrap();
+        let md = parse_metadata(html, &base);
+        assert_eq!(md.title, Some("Plain title".into()));
+        assert_eq!(md.description, Some("A page".into()));
+        assert_eq!(
+            md.favicon_url,
+            Some("https://example.com/static/favicon.png".into())
+        );
+        assert_eq!(md.canonical_url, Some("https://example.com/page".into()));
+    }
+
+    #[test]
+    fn test_parse_metadata_prefers_og_title() {
+        let html = r#"<head><title>plain</title><meta property="og:title" content="og"></head>"#;
+        let base = Url::parse("https://example.com").unwrap();
+        let md = parse_metadata(html, &base);
+        assert_eq!(md.title, Some("og".into()));
+        assert_eq!(md.description, None);
+        assert_eq!(md.favicon_url, None);
+    }
+
+    #[test]
+    fn test_is_public_addr() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2002:a9fe:a9fe::1",
+            "64:ff9b::10.0.0.1",
+            "64:ff9b:1::a00:1",
+            "2001:0:4136:e378::1",
+        ] {
+            assert!(!is_public_addr(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "93.184.216.34",
+            "2606:4700::1111",
+            "::ffff:93.184.216.34",
+            "2002:5db8:d822::1",
+            "64:ff9b::93.184.216.34",
+        ] {
+            assert!(is_public_addr(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_fetcher_refuses_private() {
+        let fetcher = HttpMetadataFetcher::new(false);
+        for url in [
+            "http://127.0.0.1:8080/",
+            "http://localhost/",
+            "http://[::1]/",
+        ] {
+            assert!(matches!(
+                fetcher.fetch(url).await,
+                Err(FetchError::ForbiddenAddress)
+            ));
+        }
+        assert!(matches!(
+            fetcher.fetch("file:///etc/passwd").await,
+            Err(FetchError::InvalidUrl)
+        ));
+    }
+}
@@ -1,4 +1,6 @@
+pub mod group;
 pub mod invite;
+pub mod password_reset;
 pub mod session;
 pub mod tab;
 pub mod tag;
@@ -0,0 +1,67 @@
+use super::tab::Tab;
+use diesel::{deserialize::Queryable, Insertable, Selectable};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Queryable, Selectable, Serialize, PartialEq)]
+#[diesel(table_name = crate::schema::tab_groups)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(test, derive(Clone))]
+pub struct TabGroup {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub position: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+// a group of tabs saved at once, e.g. a browser window
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewTabGroup {
+    pub user_id: String,
+    pub name: String,
+    pub tabs: Vec<NewGroupTab>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NewGroupTab {
+    pub url: String,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::tab_groups)]
+pub struct NewTabGroupRecord {
+    pub user_id: String,
+    pub name: String,
+    pub position: i32,
+}
+
+// tabs in the group's order
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabGroupWithTabs {
+    pub group: TabGroup,
+    pub tabs: Vec<Tab>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabGroupUpdate {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReorderGroupsRequest {
+    pub group_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReorderGroupTabsRequest {
+    pub tab_ids: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DeleteGroupRequest {
+    // move the group's tabs to the trash too, rather than just ungrouping them
+    pub delete_tabs: Option<bool>,
+}
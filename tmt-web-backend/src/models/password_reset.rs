@@ -0,0 +1,28 @@
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Queryable, Selectable, Serialize)]
+#[diesel(table_name = crate::schema::password_resets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PasswordReset {
+    pub id: String,
+    pub user_id: String,
+    pub expires: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::password_resets)]
+pub struct NewPasswordReset {
+    pub user_id: String,
+    pub token_hash: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewPasswordPayload {
+    pub password: String,
+}
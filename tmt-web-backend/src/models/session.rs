@@ -1,20 +1,33 @@
 use axum::{
     async_trait,
-    extract::{FromRef, FromRequestParts},
-    http::request::Parts,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{
+        header::{HeaderName, USER_AGENT},
+        request::Parts,
+        HeaderMap,
+    },
 };
 use diesel::{associations::Identifiable, deserialize::Queryable, Insertable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
 
 use crate::{auth::get_claims, types::AppError, AppState};
 
-#[derive(Debug, Queryable, Selectable, Identifiable, PartialEq)]
+#[derive(Debug, Queryable, Selectable, Identifiable, PartialEq, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::sessions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Session {
     pub id: String,
     pub user_id: String,
     pub expires: chrono::NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    // only bumped once a minute or so, to spare a write per request
+    pub last_seen_at: chrono::NaiveDateTime,
 }
 
 #[async_trait]
@@ -38,4 +51,134 @@ where
 #[diesel(table_name = crate::schema::sessions)]
 pub struct NewSession {
     pub user_id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+// What's known about the client logging in, to tell its sessions apart. Informational only: the
+// user agent can be made up, and the ip is only as good as the proxies in front of us
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+const MAX_USER_AGENT_LEN: usize = 512;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let st = AppState::from_ref(state);
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip = client_ip(&parts.headers, peer, st.trusted_proxies()).map(|ip| ip.to_string());
+        Ok(Self { user_agent, ip })
+    }
+}
+
+// Whoever connected, unless that's one of our proxies: then `X-Forwarded-For` is read from the
+// right, skipping further proxies of ours, to the first address they didn't add themselves.
+// Anything left of that came from the client and could say anything.
+fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+    let mut forwarded = headers
+        .get_all(HeaderName::from_static("x-forwarded-for"))
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    forwarded.reverse();
+    for ip in forwarded {
+        match ip {
+            Some(ip) if trusted.contains(&ip) => continue,
+            Some(ip) => return Some(ip),
+            None => return None,
+        }
+    }
+    Some(peer)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: Session,
+    // whether it's the session making the request
+    pub current: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionsDeletedResponse {
+    pub deleted: usize,
+}
+
+#[derive(Debug, Queryable, Selectable, Identifiable, PartialEq)]
+#[diesel(table_name = crate::schema::refresh_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RefreshToken {
+    pub id: String,
+    pub session_id: String,
+    pub created_at: chrono::NaiveDateTime,
+    // set once the token has been exchanged; presenting it again revokes the session
+    pub used_at: Option<chrono::NaiveDateTime>,
+    // the token itself is only ever seen by the client
+    pub token_hash: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::refresh_tokens)]
+pub struct NewRefreshToken {
+    pub session_id: String,
+    pub token_hash: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let proxy = ip("10.0.0.1");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "203.0.113.9, 198.51.100.7, 10.0.0.2".parse().unwrap(),
+        );
+        // not from a proxy of ours, the header is ignored
+        assert_eq!(
+            client_ip(&headers, Some(ip("192.0.2.1")), &[]),
+            Some(ip("192.0.2.1"))
+        );
+        assert_eq!(client_ip(&headers, None, &[proxy]), None);
+        // the client's own claim, 203.0.113.9, is never reached
+        assert_eq!(
+            client_ip(&headers, Some(proxy), &[proxy, ip("10.0.0.2")]),
+            Some(ip("198.51.100.7"))
+        );
+        assert_eq!(
+            client_ip(&HeaderMap::new(), Some(proxy), &[proxy]),
+            Some(proxy)
+        );
+        headers.insert("x-forwarded-for", "nonsense".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(proxy), &[proxy]), None);
+    }
 }
@@ -1,8 +1,47 @@
 use super::tag::{MaybeNewTag, Tag};
+use crate::{metadata::PageMetadata, types::AppError, urls::normalize_url};
 use diesel::{
-    associations::Associations, deserialize::Queryable, Identifiable, Insertable, Selectable,
+    associations::Associations, deserialize::Queryable, AsChangeset, Identifiable, Insertable,
+    Selectable,
 };
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use url::Url;
+
+#[derive(
+    Debug, Default, Clone, Copy, diesel_derive_enum::DbEnum, Serialize, Deserialize, PartialEq,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::TabStatus"]
+#[serde(rename_all = "snake_case")]
+pub enum TabStatus {
+    #[default]
+    Unread,
+    Read,
+    Archived,
+}
+
+impl TabStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TabStatus::Unread => "unread",
+            TabStatus::Read => "read",
+            TabStatus::Archived => "archived",
+        }
+    }
+}
+
+impl FromStr for TabStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unread" => Ok(TabStatus::Unread),
+            "read" => Ok(TabStatus::Read),
+            "archived" => Ok(TabStatus::Archived),
+            _ => Err(()),
+        }
+    }
+}
 
 #[derive(Debug, Identifiable, Deserialize, Queryable, Selectable, Serialize, PartialEq)]
 #[diesel(table_name = crate::schema::tabs)]
@@ -13,6 +52,18 @@ pub struct Tab {
     pub user_id: String,
     pub url: String,
     pub notes: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub modified_at: chrono::NaiveDateTime,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub favicon_url: Option<String>,
+    pub canonical_url: Option<String>,
+    pub status: TabStatus,
+    // only ever set for tabs in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+    pub group_id: Option<String>,
+    pub group_position: Option<i32>,
 }
 
 #[derive(Debug, Identifiable, Selectable, Queryable, Associations)]
@@ -25,13 +76,136 @@ pub struct TabTag {
     pub tag_id: String,
 }
 
-#[derive(Debug, Insertable, Deserialize, Serialize)]
-#[diesel(table_name = crate::schema::tabs)]
+#[derive(Debug, Deserialize, Serialize)]
 #[cfg_attr(test, derive(fake::Dummy))]
 pub struct NewTab {
     pub user_id: String,
     pub url: String,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::tabs)]
+pub struct NewTabRecord {
+    pub user_id: String,
+    pub url: String,
+    pub notes: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub favicon_url: Option<String>,
+    pub canonical_url: Option<String>,
+    pub normalized_url: Option<String>,
+    // `None` means now
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub modified_at: Option<chrono::NaiveDateTime>,
+    pub group_id: Option<String>,
+    pub group_position: Option<i32>,
+}
+
+impl NewTabRecord {
+    // a title supplied by the user wins over the fetched one
+    pub fn new(tab: NewTab, metadata: PageMetadata) -> Self {
+        Self {
+            user_id: tab.user_id,
+            normalized_url: Some(normalize_url(&tab.url)),
+            url: tab.url,
+            notes: tab.notes,
+            title: tab.title.or(metadata.title),
+            description: metadata.description,
+            favicon_url: metadata.favicon_url,
+            canonical_url: metadata.canonical_url,
+            created_at: None,
+            modified_at: None,
+            group_id: None,
+            group_position: None,
+        }
+    }
+}
+
+// `None` leaves a field untouched; an explicit `null` clears `notes`/`title`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "crate::types::deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notes: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "crate::types::deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub title: Option<Option<String>>,
+}
+
+impl TabUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.url.is_none() && self.notes.is_none() && self.title.is_none()
+    }
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = crate::schema::tabs)]
+pub struct TabChangeset {
+    pub url: Option<String>,
+    pub notes: Option<Option<String>>,
+    pub title: Option<Option<String>>,
+    pub normalized_url: Option<Option<String>>,
+    pub description: Option<Option<String>>,
+    pub favicon_url: Option<Option<String>>,
+    pub canonical_url: Option<Option<String>>,
+}
+
+// `400` for a url that isn't an absolute http(s) one
+impl TryFrom<TabUpdate> for TabChangeset {
+    type Error = AppError;
+
+    fn try_from(update: TabUpdate) -> Result<Self, Self::Error> {
+        let url = update.url.map(|u| u.trim().to_string());
+        if let Some(url) = &url {
+            if !matches!(Url::parse(url), Ok(u) if matches!(u.scheme(), "http" | "https")) {
+                return Err(AppError::BadRequest);
+            }
+        }
+        Ok(Self {
+            normalized_url: url.as_deref().map(|u| Some(normalize_url(u))),
+            url,
+            notes: update.notes,
+            title: update.title,
+            description: None,
+            favicon_url: None,
+            canonical_url: None,
+        })
+    }
+}
+
+impl TabChangeset {
+    // replaces whatever was fetched for the old url; a title in the update wins, as for new tabs
+    pub fn with_metadata(self, metadata: PageMetadata) -> Self {
+        Self {
+            title: self.title.or(Some(metadata.title)),
+            description: Some(metadata.description),
+            favicon_url: Some(metadata.favicon_url),
+            canonical_url: Some(metadata.canonical_url),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Trash {
+    pub tabs: Vec<Tab>,
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabStatusUpdate {
+    pub status: TabStatus,
 }
 
 #[derive(Debug, Insertable, Deserialize, Serialize)]
@@ -86,3 +260,113 @@ pub struct NewTabWithTags {
     pub tab: NewTab,
     pub tags: Vec<MaybeNewTag>,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteTabsRequest {
+    pub tab_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabsDeletedResponse {
+    pub deleted: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchTabsRequest {
+    pub tab_ids: Vec<String>,
+    #[serde(flatten)]
+    pub op: BatchTabOp,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchTabOp {
+    AddTags { tag_ids: Vec<String> },
+    RemoveTags { tag_ids: Vec<String> },
+    // moves the tabs to the trash
+    Delete,
+    SetStatus { status: TabStatus },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchTabOutcome {
+    Updated,
+    // e.g. adding a tag the tab already has
+    Unchanged,
+    // missing, trashed or someone else's; nothing was done to it
+    NotFound,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchTabResult {
+    pub tab_id: String,
+    pub outcome: BatchTabOutcome,
+}
+
+// one result per requested tab, in request order
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchTabsResponse {
+    pub results: Vec<BatchTabResult>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MergeTabsRequest {
+    // the tab folded into (and then deleted in favor of) the one in the path
+    pub source_tab_id: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ImportRequest {
+    pub dry_run: Option<bool>,
+}
+
+// for a dry run, what would have been created
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub new_tabs: usize,
+    pub new_tags: usize,
+    pub duplicates: Vec<ImportDuplicate>,
+    // links that aren't http(s), e.g. bookmarklets
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportDuplicate {
+    pub url: String,
+    // `None` when the url was already earlier in the file
+    pub existing_tab_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+    // Netscape bookmark file, with tags as folders
+    Html,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ExportRequest {
+    pub format: Option<ExportFormat>,
+}
@@ -1,5 +1,7 @@
-use diesel::{deserialize::Queryable, Insertable, Selectable};
+use crate::types::AppError;
+use diesel::{deserialize::Queryable, AsChangeset, Insertable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Deserialize, Queryable, Selectable, Serialize, PartialEq)]
 #[diesel(table_name = crate::schema::tags)]
@@ -9,6 +11,22 @@ pub struct Tag {
     pub id: String,
     pub user_id: String,
     pub tag: String,
+    pub parent_id: Option<String>,
+    // `#rrggbb`
+    pub color: Option<String>,
+    pub description: Option<String>,
+    pub pinned: bool,
+    // only ever set for tags in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+// `tab_count` only counts tabs that aren't in the trash
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TagWithCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub tab_count: i64,
 }
 
 #[derive(Debug, Deserialize, Insertable, Serialize)]
@@ -36,3 +54,195 @@ pub struct MaybeNewTag {
     pub user_id: String,
     pub tag: String,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagsDeletedResponse {
+    pub deleted: usize,
+}
+
+// `None` leaves a field untouched; an explicit `null` clears `color`/`description`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TagUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "crate::types::deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "crate::types::deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<bool>,
+}
+
+impl TagUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none()
+            && self.color.is_none()
+            && self.description.is_none()
+            && self.pinned.is_none()
+    }
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = crate::schema::tags)]
+pub struct TagChangeset {
+    pub tag: Option<String>,
+    pub color: Option<Option<String>>,
+    pub description: Option<Option<String>>,
+    pub pinned: Option<bool>,
+}
+
+// `400` for a blank name or a color that isn't `#rrggbb`; a blank description clears it
+impl TryFrom<TagUpdate> for TagChangeset {
+    type Error = AppError;
+
+    fn try_from(update: TagUpdate) -> Result<Self, Self::Error> {
+        if update.tag.as_deref().is_some_and(|t| t.trim().is_empty()) {
+            return Err(AppError::BadRequest);
+        }
+        let color = match update.color {
+            Some(Some(c)) => Some(Some(parse_color(&c).ok_or(AppError::BadRequest)?)),
+            color => color,
+        };
+        let description = update
+            .description
+            .map(|d| d.filter(|d| !d.trim().is_empty()));
+        Ok(Self {
+            tag: update.tag,
+            color,
+            description,
+            pinned: update.pinned,
+        })
+    }
+}
+
+fn parse_color(color: &str) -> Option<String> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(color.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MergeTagsRequest {
+    // the tag folded into (and then deleted in favor of) the one in the path
+    pub source_tag_id: String,
+}
+
+// `null` makes the tag a top-level one
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagParentUpdate {
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TagNode {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    // Nests `tags` under their parents, keeping their order among siblings. Tags whose parent
+    // isn't in `tags` (e.g. it's in the trash) end up at the top level.
+    pub fn tree(tags: Vec<Tag>) -> Vec<TagNode> {
+        let ids: HashSet<String> = tags.iter().map(|t| t.id.clone()).collect();
+        let mut children: HashMap<String, Vec<Tag>> = HashMap::new();
+        let mut roots: Vec<Tag> = Vec::new();
+        for tag in tags {
+            match &tag.parent_id {
+                Some(parent_id) if ids.contains(parent_id) => {
+                    children.entry(parent_id.clone()).or_default().push(tag)
+                }
+                _ => roots.push(tag),
+            }
+        }
+        roots
+            .into_iter()
+            .map(|tag| Self::build(tag, &mut children))
+            .collect()
+    }
+
+    fn build(tag: Tag, children: &mut HashMap<String, Vec<Tag>>) -> TagNode {
+        let kids = children.remove(&tag.id).unwrap_or_default();
+        TagNode {
+            children: kids
+                .into_iter()
+                .map(|kid| Self::build(kid, children))
+                .collect(),
+            tag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(id: &str, parent_id: Option<&str>) -> Tag {
+        Tag {
+            id: id.into(),
+            user_id: "user".into(),
+            tag: id.into(),
+            parent_id: parent_id.map(String::from),
+            color: None,
+            description: None,
+            pinned: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let tags = vec![
+            tag("lang", None),
+            tag("lang/go", Some("lang")),
+            tag("lang/rust", Some("lang")),
+            tag("lang/rust/async", Some("lang/rust")),
+            tag("orphan", Some("trashed")),
+        ];
+        let tree = TagNode::tree(tags);
+        let names = |nodes: &[TagNode]| -> Vec<String> {
+            nodes.iter().map(|n| n.tag.tag.clone()).collect()
+        };
+        assert_eq!(names(&tree), vec!["lang", "orphan"]);
+        assert_eq!(names(&tree[0].children), vec!["lang/go", "lang/rust"]);
+        assert_eq!(
+            names(&tree[0].children[1].children),
+            vec!["lang/rust/async"]
+        );
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_tag_changeset() {
+        let update: TagUpdate =
+            serde_json::from_str(r##"{"color": "#FF8800", "description": " "}"##).unwrap();
+        let cs = TagChangeset::try_from(update).unwrap();
+        assert_eq!(cs.color, Some(Some("#ff8800".into())));
+        assert_eq!(cs.description, Some(None));
+        assert_eq!(cs.tag, None);
+        assert_eq!(cs.pinned, None);
+
+        let update: TagUpdate = serde_json::from_str(r#"{"color": null}"#).unwrap();
+        assert_eq!(TagChangeset::try_from(update).unwrap().color, Some(None));
+
+        for bad in [
+            r#"{"color": "red"}"#,
+            r##"{"color": "#ff88"}"##,
+            r#"{"tag": ""}"#,
+        ] {
+            let update: TagUpdate = serde_json::from_str(bad).unwrap();
+            assert!(TagChangeset::try_from(update).is_err());
+        }
+    }
+}
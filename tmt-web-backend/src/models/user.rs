@@ -29,6 +29,12 @@ pub struct NewConfirmedUser {
     pub confirmed: bool,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordChangePayload {
+    pub current_password: String,
+    pub new_password: String,
+}
+
 #[derive(Debug, Queryable, Selectable, Serialize)]
 #[diesel(table_name = crate::schema::users)]
 pub struct CreatedUser {
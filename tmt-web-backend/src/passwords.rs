@@ -0,0 +1,146 @@
+use crate::types::AppError;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher as _, SaltString},
+    Algorithm, Argon2, Params, PasswordVerifier as _, Version,
+};
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_PASSWORD_LEN: usize = 128;
+
+// lengths in characters. Only checked when a password is set, existing ones keep working
+pub fn check_password_policy(password: &str) -> Result<(), AppError> {
+    let len = password.chars().count();
+    if (MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&len) && !password.trim().is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest)
+    }
+}
+
+// Argon2id, with the cost configurable so tests don't crawl at production settings. The default
+// is the `argon2` crate's, per OWASP: 19 MiB, 2 iterations, 1 lane
+#[derive(Clone, Debug, Default)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+impl PasswordHasher {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, AppError> {
+        let params = Params::new(memory_kib, iterations, parallelism, None).map_err(|e| {
+            tracing::error!("invalid argon2 params: {:?}", e);
+            AppError::InternalServerError
+        })?;
+        Ok(Self { params })
+    }
+    // panics
+    #[cfg(not(test))]
+    pub fn from_env() -> Self {
+        let cost = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map_or(default, |v| {
+                    v.parse().unwrap_or_else(|_| panic!("invalid {}", name))
+                })
+        };
+        Self::new(
+            cost("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST),
+            cost("ARGON2_ITERATIONS", Params::DEFAULT_T_COST),
+            cost("ARGON2_PARALLELISM", Params::DEFAULT_P_COST),
+        )
+        .expect("invalid argon2 cost")
+    }
+    // as cheap as argon2 allows
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self::new(Params::MIN_M_COST, Params::MIN_T_COST, Params::MIN_P_COST).unwrap()
+    }
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+    // hashing is deliberately slow, so it happens off the async workers
+    pub async fn hash(&self, password: String) -> Result<String, AppError> {
+        let argon2 = self.argon2();
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map(|h| h.to_string())
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("error hashing password: {:?}", e);
+            AppError::InternalServerError
+        })?
+        .map_err(|e| {
+            tracing::error!("error hashing password: {:?}", e);
+            AppError::InternalServerError
+        })
+    }
+    // `false` for anything that isn't an argon2 hash, e.g. a legacy md5-crypt one
+    pub async fn verify(&self, password: String, hash: String) -> Result<bool, AppError> {
+        let argon2 = self.argon2();
+        tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&hash)
+                .is_ok_and(|h| argon2.verify_password(password.as_bytes(), &h).is_ok())
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("error verifying password: {:?}", e);
+            AppError::InternalServerError
+        })
+    }
+    pub fn is_legacy(hash: &str) -> bool {
+        !hash.starts_with("$argon2")
+    }
+    // legacy hashes, and argon2 ones made with other settings
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || Params::try_from(&hash).map_or(true, |p| {
+                p.m_cost() != self.params.m_cost()
+                    || p.t_cost() != self.params.t_cost()
+                    || p.p_cost() != self.params.p_cost()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let hasher = PasswordHasher::for_tests();
+        let hash = hasher.hash("hunter2".into()).await.unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!PasswordHasher::is_legacy(&hash));
+        assert!(hasher.verify("hunter2".into(), hash.clone()).await.unwrap());
+        assert!(!hasher.verify("hunter3".into(), hash.clone()).await.unwrap());
+        assert!(!hasher.needs_rehash(&hash));
+
+        let costlier = PasswordHasher::new(Params::MIN_M_COST * 2, 2, 1).unwrap();
+        assert!(costlier.needs_rehash(&hash));
+        // settings are read from the hash, so old ones still verify
+        assert!(costlier.verify("hunter2".into(), hash).await.unwrap());
+
+        let legacy = "$1$abcdefgh$012345678901234567890.";
+        assert!(PasswordHasher::is_legacy(legacy));
+        assert!(hasher.needs_rehash(legacy));
+        assert!(!hasher
+            .verify("hunter2".into(), legacy.into())
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn test_password_policy() {
+        assert!(check_password_policy("correct horse").is_ok());
+        assert!(check_password_policy("ünïcödé!").is_ok());
+        assert!(check_password_policy("short").is_err());
+        assert!(check_password_policy("        ").is_err());
+        assert!(check_password_policy(&"a".repeat(MAX_PASSWORD_LEN + 1)).is_err());
+    }
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod _test_utils;
 pub mod auth;
+pub mod export;
+pub mod groups;
+pub mod import;
 pub mod misc;
 pub mod tabs;
 pub mod tags;
+pub mod trash;
 pub mod users;
@@ -1,20 +1,35 @@
 use crate::{
     db::{
-        sessions::{delete_session, new_session},
-        validate_password,
+        sessions::{self, delete_session, new_refresh_token, new_session, refresh_session},
+        users, validate_password,
     },
-    types::{AppError, AppState, AuthBody, AuthPayload, Claims, LogoutResult},
+    mailer::Email,
+    models::password_reset::{NewPasswordPayload, PasswordResetRequest},
+    models::session::{ClientInfo, Session},
+    passwords::check_password_policy,
+    types::{AppError, AppState, AuthBody, AuthPayload, Claims, LogoutResult, RefreshRequest},
 };
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, post},
+    Json, Router,
+};
+use chrono::Utc;
 
 pub fn auth_router() -> Router<AppState> {
     Router::new()
         .route("/authorize", post(authorize))
+        .route("/token/refresh", post(refresh_token))
         .route("/logout", post(logout))
+        .route("/sessions/:session_id", delete(revoke_session))
+        .route("/password-reset", post(request_password_reset))
+        .route("/password-reset/:token", post(reset_password))
 }
 
 pub(crate) async fn authorize(
     State(st): State<AppState>,
+    client: ClientInfo,
     Json(payload): Json<AuthPayload>,
 ) -> Result<Json<AuthBody>, AppError> {
     if payload.client_id.is_empty() || payload.client_secret.is_empty() {
@@ -22,19 +37,38 @@ pub(crate) async fn authorize(
     }
     let conn = st.conn().await?;
     let clid = payload.client_id.clone();
-    let pwd_valid = validate_password(conn, clid, payload.client_secret).await?;
+    let pwd_valid = validate_password(conn, st.hasher(), clid, payload.client_secret).await?;
     if !pwd_valid {
         Err(AppError::BadRequest)
     } else {
-        let session = new_session(st.pool(), payload.client_id).await?;
-        let claims = Claims::from_session(&session);
-        let token = claims.into_token(st.encoding())?;
-        let ab = AuthBody::new(token);
-        tracing::warn!("authorize ab: {:?}", ab);
-        Ok(Json(ab))
+        let session = new_session(st.pool(), payload.client_id, client).await?;
+        let conn = st.conn().await?;
+        let refresh_token = new_refresh_token(conn, session.id.clone()).await?;
+        Ok(Json(auth_body(&st, &session, refresh_token)?))
     }
 }
 
+// Works with an expired access token, which is the point
+pub(crate) async fn refresh_token(
+    State(st): State<AppState>,
+    Json(RefreshRequest { refresh_token }): Json<RefreshRequest>,
+) -> Result<Json<AuthBody>, AppError> {
+    let conn = st.conn().await?;
+    let (session, refresh_token) = refresh_session(conn, refresh_token).await?;
+    Ok(Json(auth_body(&st, &session, refresh_token)?))
+}
+
+fn auth_body(
+    st: &AppState,
+    session: &Session,
+    refresh_token: String,
+) -> Result<AuthBody, AppError> {
+    let claims = Claims::from_session(session);
+    let expires_in = (claims.exp - Utc::now().timestamp()).max(0);
+    let access_token = claims.into_token(st.encoding())?;
+    Ok(AuthBody::new(access_token, expires_in, refresh_token))
+}
+
 pub(crate) async fn logout(
     State(st): State<AppState>,
     claims: Claims,
@@ -45,20 +79,67 @@ pub(crate) async fn logout(
     Ok(axum::Json(LogoutResult::new(sess_id)))
 }
 
+// signs one of the user's other devices out; their own session works too, like `/logout`
+pub(crate) async fn revoke_session(
+    State(st): State<AppState>,
+    session: Session,
+    Path(session_id): Path<String>,
+) -> Result<Json<LogoutResult>, AppError> {
+    let conn = st.conn().await?;
+    sessions::revoke_session(conn, session.user_id, session_id.clone()).await?;
+    Ok(Json(LogoutResult::new(session_id)))
+}
+
+// `200` whether or not there's an account with the email, so it can't be used to find out
+pub(crate) async fn request_password_reset(
+    State(st): State<AppState>,
+    Json(PasswordResetRequest { email }): Json<PasswordResetRequest>,
+) -> Result<StatusCode, AppError> {
+    let conn = st.conn().await?;
+    if let Some((reset, code)) = users::new_password_reset(conn, email.clone()).await? {
+        let mail = Email {
+            to: email,
+            subject: "Reset your password".into(),
+            body: format!(
+                "Someone asked to reset the password for this address. If it wasn't you, \
+                 ignore this email. The code below works once, until {} UTC:\n\n{}",
+                reset.expires.format("%Y-%m-%d %H:%M"),
+                code
+            ),
+        };
+        // failing here would give away that the account exists
+        if let Err(e) = st.mailer().send(mail).await {
+            tracing::error!("error sending password reset: {:?}", e);
+        }
+    }
+    Ok(StatusCode::OK)
+}
+
+pub(crate) async fn reset_password(
+    State(st): State<AppState>,
+    Path(token): Path<String>,
+    Json(NewPasswordPayload { password }): Json<NewPasswordPayload>,
+) -> Result<StatusCode, AppError> {
+    check_password_policy(&password)?;
+    let hash = st.hasher().hash(password).await?;
+    let conn = st.conn().await?;
+    users::reset_password(conn, token, hash).await?;
+    Ok(StatusCode::OK)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
         db::{
-            sessions::{delete_user_sessions, get_session},
-            users::{deconfirm_user, new_user_confirmed},
+            sessions::{delete_user_sessions, expire_session, get_session},
+            users::{deconfirm_user, get_user, new_user_confirmed, set_legacy_password},
         },
         models::user::NewConfirmedUser,
         routes::_test_utils::test_app,
-        types::test_pool_from_env,
+        types::{test_pool_from_env, ACCESS_TOKEN_TTL_SECS},
     };
     use fake::{Fake, Faker};
-    use http::StatusCode;
 
     #[test_log::test(tokio::test)]
     async fn test_authorize_ok() -> anyhow::Result<()> {
@@ -92,6 +173,60 @@ mod test {
         let resp_json = resp.json::<AuthBody>();
         assert!(!resp_json.access_token.is_empty());
         assert_eq!(resp_json.token_type, String::from("Bearer"));
+        let logged = format!("{:?}", resp_json);
+        assert!(!logged.contains(&resp_json.access_token));
+        assert!(!logged.contains(&resp_json.refresh_token));
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_authorize_upgrades_legacy_hash() -> anyhow::Result<()> {
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create user
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        let pwd = user_data.password.clone();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let uid = user.id.clone();
+        let c = pool.get().await?;
+        set_legacy_password(c, uid.clone(), pwd.clone()).await?;
+        let c = pool.get().await?;
+        let legacy = get_user(c, uid.clone()).await?;
+
+        // post /authorize
+        let bad_login = AuthPayload {
+            client_id: user_email.clone(),
+            client_secret: "bad_password".to_string(),
+        };
+        let bad_resp = server.post("/authorize").json(&bad_login).await;
+        let c = pool.get().await?;
+        let after_bad = get_user(c, uid.clone()).await?;
+        let login_data = AuthPayload {
+            client_id: user_email,
+            client_secret: pwd,
+        };
+        let resp = server.post("/authorize").json(&login_data).await;
+        let c = pool.get().await?;
+        let upgraded = get_user(c, uid.clone()).await?;
+        let again = server.post("/authorize").json(&login_data).await;
+
+        // cleanup
+        let c = pool.get().await?;
+        deconfirm_user(c, uid.clone()).await?;
+        let c = pool.get().await?;
+        delete_user_sessions(c, uid.clone()).await?;
+
+        // assert
+        assert!(legacy.password.starts_with("$1$"));
+        bad_resp.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(after_bad.password, legacy.password);
+        resp.assert_status_ok();
+        assert!(upgraded.password.starts_with("$argon2id$"));
+        again.assert_status_ok();
         Ok(())
     }
 
@@ -202,7 +337,7 @@ mod test {
         let uid = user.id.clone();
 
         let p = pool.clone();
-        let session = new_session(p, user_email.clone()).await?;
+        let session = new_session(p, user_email.clone(), Default::default()).await?;
         let sid = session.id.clone();
         let token = Claims::from_session(&session).test_to_token()?;
 
@@ -229,4 +364,382 @@ mod test {
 
         Ok(())
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_password_reset() -> anyhow::Result<()> {
+        use crate::mailer::FakeMailer;
+
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create user
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        let old_pwd = user_data.password.clone();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let uid = user.id.clone();
+        let session = new_session(pool.clone(), user_email.clone(), Default::default()).await?;
+
+        // ask twice, only the second code should work
+        for _ in 0..2 {
+            server
+                .post("/password-reset")
+                .json(&PasswordResetRequest {
+                    email: user_email.clone(),
+                })
+                .await
+                .assert_status_ok();
+        }
+        let sent = FakeMailer::sent_to(&user_email);
+        let codes: Vec<String> = sent
+            .iter()
+            .filter_map(|m| m.body.lines().last().map(String::from))
+            .collect();
+        let new_pwd = "a much better password".to_string();
+        let stale = server
+            .post(&format!("/password-reset/{}", &codes[0]))
+            .json(&NewPasswordPayload {
+                password: new_pwd.clone(),
+            })
+            .await;
+        let too_short = server
+            .post(&format!("/password-reset/{}", &codes[1]))
+            .json(&NewPasswordPayload {
+                password: "short".into(),
+            })
+            .await;
+        let reset = server
+            .post(&format!("/password-reset/{}", &codes[1]))
+            .json(&NewPasswordPayload {
+                password: new_pwd.clone(),
+            })
+            .await;
+        let reused = server
+            .post(&format!("/password-reset/{}", &codes[1]))
+            .json(&NewPasswordPayload {
+                password: new_pwd.clone(),
+            })
+            .await;
+        let c = pool.get().await?;
+        let old_session = get_session(c, session.id.clone()).await?;
+        let old_login = server
+            .post("/authorize")
+            .json(&AuthPayload {
+                client_id: user_email.clone(),
+                client_secret: old_pwd,
+            })
+            .await;
+        let new_login = server
+            .post("/authorize")
+            .json(&AuthPayload {
+                client_id: user_email.clone(),
+                client_secret: new_pwd,
+            })
+            .await;
+
+        // cleanup
+        let c = pool.get().await?;
+        deconfirm_user(c, uid.clone()).await?;
+        let c = pool.get().await?;
+        delete_user_sessions(c, uid.clone()).await?;
+
+        // assert
+        assert_eq!(sent.len(), 2);
+        stale.assert_status(StatusCode::NOT_FOUND);
+        too_short.assert_status(StatusCode::BAD_REQUEST);
+        reset.assert_status_ok();
+        reused.assert_status(StatusCode::NOT_FOUND);
+        assert!(old_session.is_none());
+        old_login.assert_status(StatusCode::BAD_REQUEST);
+        new_login.assert_status_ok();
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_password_reset_unknown_email() -> anyhow::Result<()> {
+        use crate::mailer::FakeMailer;
+
+        let server = test_app(auth_router())?;
+        let email = Faker.fake::<NewConfirmedUser>().email;
+
+        let resp = server
+            .post("/password-reset")
+            .json(&PasswordResetRequest {
+                email: email.clone(),
+            })
+            .await;
+        let bad_token = server
+            .post("/password-reset/not-a-token")
+            .json(&NewPasswordPayload {
+                password: "a much better password".into(),
+            })
+            .await;
+
+        resp.assert_status_ok();
+        assert!(FakeMailer::sent_to(&email).is_empty());
+        bad_token.assert_status(StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_refresh_token() -> anyhow::Result<()> {
+        use http::header;
+
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create user
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        let pwd = user_data.password.clone();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let uid = user.id.clone();
+
+        let login = server
+            .post("/authorize")
+            .json(&AuthPayload {
+                client_id: user.email.clone(),
+                client_secret: pwd,
+            })
+            .await
+            .json::<AuthBody>();
+        let refreshed = server
+            .post("/token/refresh")
+            .json(&RefreshRequest {
+                refresh_token: login.refresh_token.clone(),
+            })
+            .await;
+        let refreshed_body = refreshed.json::<AuthBody>();
+        let bearer = format!("Bearer {}", refreshed_body.access_token);
+        let logout = server
+            .post("/logout")
+            .add_header(
+                header::AUTHORIZATION,
+                header::HeaderValue::from_str(&bearer)?,
+            )
+            .await;
+        // logging out again revokes the new refresh token along with the session
+        let after_logout = server
+            .post("/token/refresh")
+            .json(&RefreshRequest {
+                refresh_token: refreshed_body.refresh_token.clone(),
+            })
+            .await;
+
+        // cleanup
+        let c = pool.get().await?;
+        deconfirm_user(c, uid.clone()).await?;
+        let c = pool.get().await?;
+        delete_user_sessions(c, uid.clone()).await?;
+
+        // assert
+        assert!(login.expires_in > 0 && login.expires_in <= ACCESS_TOKEN_TTL_SECS);
+        refreshed.assert_status_ok();
+        assert_ne!(refreshed_body.refresh_token, login.refresh_token);
+        assert!(refreshed_body.expires_in > 0);
+        logout.assert_status_ok();
+        after_logout.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_refresh_token_reuse() -> anyhow::Result<()> {
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create user
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let uid = user.id.clone();
+        let session = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let c = pool.get().await?;
+        let first = new_refresh_token(c, session.id.clone()).await?;
+
+        let refresh = |token: &String| {
+            server.post("/token/refresh").json(&RefreshRequest {
+                refresh_token: token.clone(),
+            })
+        };
+        let second = refresh(&first).await.json::<AuthBody>().refresh_token;
+        // the first token turning up again means it leaked
+        let reused = refresh(&first).await;
+        let c = pool.get().await?;
+        let revoked = get_session(c, session.id.clone()).await?;
+        let after_reuse = refresh(&second).await;
+        let unknown = refresh(&"not-a-token".to_string()).await;
+
+        // cleanup
+        let c = pool.get().await?;
+        deconfirm_user(c, uid.clone()).await?;
+        let c = pool.get().await?;
+        delete_user_sessions(c, uid.clone()).await?;
+
+        // assert
+        reused.assert_status(StatusCode::BAD_REQUEST);
+        assert!(revoked.is_none());
+        after_reuse.assert_status(StatusCode::BAD_REQUEST);
+        unknown.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_expired_session() -> anyhow::Result<()> {
+        use http::header;
+
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create user
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let uid = user.id.clone();
+        let session = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let c = pool.get().await?;
+        let refresh_token = new_refresh_token(c, session.id.clone()).await?;
+
+        // an access token past its `exp` is turned away before the session is even looked up
+        let mut stale_claims = Claims::from_session(&session);
+        stale_claims.exp = Utc::now().timestamp() - 3600;
+        let bearer = format!("Bearer {}", stale_claims.test_to_token()?);
+        let stale = server
+            .post("/logout")
+            .add_header(
+                header::AUTHORIZATION,
+                header::HeaderValue::from_str(&bearer)?,
+            )
+            .await;
+
+        let c = pool.get().await?;
+        expire_session(c, session.id.clone()).await?;
+        let refreshed = server
+            .post("/token/refresh")
+            .json(&RefreshRequest { refresh_token })
+            .await;
+        let c = pool.get().await?;
+        let refreshed_away = get_session(c, session.id.clone()).await?;
+
+        // logging in again doesn't hand out an expired session
+        let expiring = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let c = pool.get().await?;
+        expire_session(c, expiring.id.clone()).await?;
+        let replaced = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let c = pool.get().await?;
+        let old = get_session(c, expiring.id.clone()).await?;
+
+        // cleanup
+        let c = pool.get().await?;
+        deconfirm_user(c, uid.clone()).await?;
+        let c = pool.get().await?;
+        delete_user_sessions(c, uid.clone()).await?;
+
+        // assert
+        stale.assert_status(StatusCode::UNAUTHORIZED);
+        refreshed.assert_status(StatusCode::UNAUTHORIZED);
+        assert!(refreshed_away.is_none());
+        assert_ne!(replaced.id, expiring.id);
+        assert!(replaced.expires > Utc::now().naive_utc());
+        assert!(old.is_none());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_revoke_session() -> anyhow::Result<()> {
+        use crate::db::sessions::get_user_sessions;
+        use http::header;
+
+        let server = test_app(auth_router())?;
+        let pool = test_pool_from_env();
+
+        // create users
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        let pwd = user_data.password.clone();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = new_user_confirmed(c, user_data).await?;
+        let uid = user.id.clone();
+        let mut other_data = Faker.fake::<NewConfirmedUser>();
+        other_data.confirmed = true;
+        let c = pool.get().await?;
+        let other = new_user_confirmed(c, other_data).await?;
+        let other_session =
+            new_session(pool.clone(), other.email.clone(), Default::default()).await?;
+
+        // log in from two devices
+        let login = |user_agent: &'static str| {
+            server
+                .post("/authorize")
+                .add_header(
+                    header::USER_AGENT,
+                    header::HeaderValue::from_static(user_agent),
+                )
+                .add_header(
+                    header::HeaderName::from_static("x-forwarded-for"),
+                    header::HeaderValue::from_static("198.51.100.7, 10.0.0.1"),
+                )
+                .json(&AuthPayload {
+                    client_id: user.email.clone(),
+                    client_secret: pwd.clone(),
+                })
+        };
+        let laptop = login("laptop").await.json::<AuthBody>();
+        let phone = login("phone").await.json::<AuthBody>();
+        let c = pool.get().await?;
+        let sessions = get_user_sessions(c, uid.clone()).await?;
+        let phone_session = sessions
+            .iter()
+            .find(|s| s.user_agent.as_deref() == Some("phone"))
+            .unwrap();
+
+        let bearer = format!("Bearer {}", laptop.access_token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let revoke = |session_id: &String| {
+            server
+                .delete(&format!("/sessions/{}", session_id))
+                .add_header(header::AUTHORIZATION, header_value.clone())
+        };
+        let not_mine = revoke(&other_session.id).await;
+        let revoked = revoke(&phone_session.id).await;
+        let again = revoke(&phone_session.id).await;
+        let phone_refresh = server
+            .post("/token/refresh")
+            .json(&RefreshRequest {
+                refresh_token: phone.refresh_token.clone(),
+            })
+            .await;
+        let laptop_refresh = server
+            .post("/token/refresh")
+            .json(&RefreshRequest {
+                refresh_token: laptop.refresh_token.clone(),
+            })
+            .await;
+        let c = pool.get().await?;
+        let other_kept = get_session(c, other_session.id.clone()).await?;
+
+        // cleanup
+        for id in [uid, other.id] {
+            let c = pool.get().await?;
+            deconfirm_user(c, id.clone()).await?;
+            let c = pool.get().await?;
+            delete_user_sessions(c, id).await?;
+        }
+
+        // assert
+        assert_eq!(sessions.len(), 2);
+        // no proxy of ours in between, so the forwarded address isn't believed
+        assert_ne!(phone_session.ip.as_deref(), Some("198.51.100.7"));
+        not_mine.assert_status(StatusCode::NOT_FOUND);
+        revoked.assert_status_ok();
+        again.assert_status(StatusCode::NOT_FOUND);
+        phone_refresh.assert_status(StatusCode::BAD_REQUEST);
+        laptop_refresh.assert_status_ok();
+        assert!(other_kept.is_some());
+        Ok(())
+    }
 }
@@ -0,0 +1,274 @@
+use crate::{
+    export::write_export,
+    models::{
+        session::Session,
+        tab::{ExportFormat, ExportRequest},
+    },
+    types::{AppError, AppState},
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+pub fn export_router() -> Router<AppState> {
+    Router::new().route("/users/:user_id/export", get(export))
+}
+
+async fn export(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+    Query(ExportRequest { format }): Query<ExportRequest>,
+) -> Result<Response, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let format: ExportFormat = format.unwrap_or_default();
+    // a few batches of backpressure, the writer waits for the client beyond that
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(write_export(st.clone(), session.user_id, format, tx));
+    let disposition = format!("attachment; filename=\"tabs.{}\"", format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bookmarks::parse_bookmarks,
+        db::{sessions, tabs, tags, test_util::bulk_create_tags_from_strings, users},
+        metadata::FakeMetadataFetcher,
+        models::{
+            tab::{NewTab, NewTabTag, TabWithTags},
+            user::NewConfirmedUser,
+        },
+        routes::_test_utils::test_app,
+        types::{test_pool_from_env, Claims},
+    };
+    use deadpool_diesel::postgres::Pool;
+    use fake::{Fake, Faker};
+    use http::{header::HeaderValue, StatusCode};
+
+    // a user with one tab tagged "rust" and one untagged tab, and their bearer token
+    async fn setup(pool: Pool) -> anyhow::Result<(String, HeaderValue)> {
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let tagged = tabs::new_tab(
+            pool.clone(),
+            &FakeMetadataFetcher,
+            NewTab {
+                user_id: user_id.clone(),
+                url: "https://www.rust-lang.org/".into(),
+                notes: Some("the \"language\", mostly".into()),
+                title: None,
+            },
+        )
+        .await?;
+        tabs::new_tab(
+            pool.clone(),
+            &FakeMetadataFetcher,
+            NewTab {
+                user_id: user_id.clone(),
+                url: "https://example.com/".into(),
+                notes: None,
+                title: None,
+            },
+        )
+        .await?;
+        let c = pool.get().await?;
+        let tag = bulk_create_tags_from_strings(c, user_id.clone(), vec!["rust".into()])
+            .await?
+            .remove(0);
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(
+            c,
+            vec![NewTabTag {
+                tab_id: tagged.id,
+                tag_id: tag.id,
+            }],
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user.email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = HeaderValue::from_str(&format!("Bearer {}", token))?;
+        Ok((user_id, bearer))
+    }
+
+    async fn cleanup(pool: Pool, user_id: String) -> anyhow::Result<()> {
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_json() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(export_router())?;
+        let (user_id, bearer) = setup(pool.clone()).await?;
+        let resp = server
+            .get(&format!("/users/{}/export", &user_id))
+            .add_header(header::AUTHORIZATION, bearer)
+            .await;
+        cleanup(pool, user_id).await?;
+
+        resp.assert_status_ok();
+        assert_eq!(resp.header(header::CONTENT_TYPE), "application/json");
+        assert_eq!(
+            resp.header(header::CONTENT_DISPOSITION),
+            "attachment; filename=\"tabs.json\""
+        );
+        let exported = resp.json::<Vec<TabWithTags>>();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].tab.url, "https://www.rust-lang.org/");
+        assert_eq!(
+            exported[0].tags.iter().map(|t| &t.tag).collect::<Vec<_>>(),
+            vec!["rust"]
+        );
+        assert_eq!(exported[1].tab.url, "https://example.com/");
+        assert!(exported[1].tags.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_csv() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(export_router())?;
+        let (user_id, bearer) = setup(pool.clone()).await?;
+        let resp = server
+            .get(&format!("/users/{}/export", &user_id))
+            .add_query_params(ExportRequest {
+                format: Some(ExportFormat::Csv),
+            })
+            .add_header(header::AUTHORIZATION, bearer)
+            .await;
+        cleanup(pool, user_id).await?;
+
+        resp.assert_status_ok();
+        assert_eq!(resp.header(header::CONTENT_TYPE), "text/csv; charset=utf-8");
+        let text = resp.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "id,url,title,notes,description,favicon_url,canonical_url,status,created_at,modified_at,tags"
+        );
+        assert!(lines[1].contains(",https://www.rust-lang.org/,"));
+        assert!(lines[1].contains(",\"the \"\"language\"\", mostly\","));
+        assert!(lines[1].ends_with(",rust"));
+        assert!(lines[2].contains(",https://example.com/,"));
+        assert!(lines[2].ends_with(','));
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_html() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(export_router())?;
+        let (user_id, bearer) = setup(pool.clone()).await?;
+        let resp = server
+            .get(&format!("/users/{}/export", &user_id))
+            .add_query_params(ExportRequest {
+                format: Some(ExportFormat::Html),
+            })
+            .add_header(header::AUTHORIZATION, bearer)
+            .await;
+        cleanup(pool, user_id).await?;
+
+        resp.assert_status_ok();
+        let bookmarks = parse_bookmarks(&resp.text());
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].url, "https://www.rust-lang.org/");
+        assert_eq!(bookmarks[0].folders, vec!["rust".to_string()]);
+        assert_eq!(bookmarks[0].notes, Some("the \"language\", mostly".into()));
+        assert_eq!(bookmarks[1].url, "https://example.com/");
+        assert!(bookmarks[1].folders.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_html_multiple_tags() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(export_router())?;
+        let (user_id, bearer) = setup(pool.clone()).await?;
+        let tab = tabs::new_tab(
+            pool.clone(),
+            &FakeMetadataFetcher,
+            NewTab {
+                user_id: user_id.clone(),
+                url: "https://go.dev/".into(),
+                notes: None,
+                title: None,
+            },
+        )
+        .await?;
+        let c = pool.get().await?;
+        let tab_tags =
+            bulk_create_tags_from_strings(c, user_id.clone(), vec!["lang".into(), "google".into()])
+                .await?
+                .into_iter()
+                .map(|tag| NewTabTag {
+                    tab_id: tab.id.clone(),
+                    tag_id: tag.id,
+                })
+                .collect();
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(c, tab_tags).await?;
+        let resp = server
+            .get(&format!("/users/{}/export", &user_id))
+            .add_query_params(ExportRequest {
+                format: Some(ExportFormat::Html),
+            })
+            .add_header(header::AUTHORIZATION, bearer)
+            .await;
+        cleanup(pool, user_id).await?;
+
+        resp.assert_status_ok();
+        let bookmarks = parse_bookmarks(&resp.text());
+        let go: Vec<_> = bookmarks
+            .iter()
+            .filter(|b| b.url == "https://go.dev/")
+            .collect();
+        // once, in the first tag's folder, with every tag kept
+        assert_eq!(go.len(), 1);
+        assert_eq!(go[0].folders, vec!["google".to_string()]);
+        assert_eq!(go[0].tags, vec!["google".to_string(), "lang".to_string()]);
+        assert_eq!(bookmarks.len(), 3);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_wrong_user() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(export_router())?;
+        let (user_id, bearer) = setup(pool.clone()).await?;
+        let resp = server
+            .get(&format!("/users/{}/export", Faker.fake::<String>()))
+            .add_header(header::AUTHORIZATION, bearer)
+            .await;
+        cleanup(pool, user_id).await?;
+
+        resp.assert_status(StatusCode::FORBIDDEN);
+        Ok(())
+    }
+}
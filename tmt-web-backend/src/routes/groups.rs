@@ -0,0 +1,520 @@
+use crate::{
+    db::groups,
+    models::{
+        group::{
+            DeleteGroupRequest, NewTabGroup, ReorderGroupTabsRequest, ReorderGroupsRequest,
+            TabGroup, TabGroupUpdate, TabGroupWithTabs,
+        },
+        session::Session,
+    },
+    types::{AppError, AppState},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+
+pub fn groups_router() -> Router<AppState> {
+    Router::new()
+        .route("/groups", post(create_group))
+        .route(
+            "/groups/:group_id",
+            get(get_group).patch(rename_group).delete(delete_group),
+        )
+        .route("/groups/:group_id/tabs", put(reorder_tabs))
+        .route(
+            "/users/:user_id/groups",
+            get(user_groups).put(reorder_groups),
+        )
+}
+
+// every new tab's page gets fetched while the client waits
+const MAX_GROUP_TABS: usize = 100;
+
+async fn create_group(
+    State(st): State<AppState>,
+    session: Session,
+    Json(payload): Json<NewTabGroup>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    if payload.name.trim().is_empty() || payload.tabs.len() > MAX_GROUP_TABS {
+        return Err(AppError::BadRequest);
+    }
+    let group = groups::new_group(st.pool(), st.fetcher(), payload).await?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+async fn get_group(
+    State(st): State<AppState>,
+    session: Session,
+    Path(group_id): Path<String>,
+) -> Result<Json<TabGroupWithTabs>, AppError> {
+    let conn = st.conn().await?;
+    let group = groups::get_group_with_tabs(conn, session.user_id, group_id).await?;
+    Ok(Json(group))
+}
+
+async fn rename_group(
+    State(st): State<AppState>,
+    session: Session,
+    Path(group_id): Path<String>,
+    Json(payload): Json<TabGroupUpdate>,
+) -> Result<Json<TabGroup>, AppError> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let conn = st.conn().await?;
+    let group = groups::rename_group(conn, session.user_id, group_id, payload.name).await?;
+    Ok(Json(group))
+}
+
+async fn delete_group(
+    State(st): State<AppState>,
+    session: Session,
+    Path(group_id): Path<String>,
+    Query(req): Query<DeleteGroupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let conn = st.conn().await?;
+    groups::delete_group(
+        conn,
+        session.user_id,
+        group_id,
+        req.delete_tabs.unwrap_or(false),
+    )
+    .await?;
+    Ok(StatusCode::OK)
+}
+
+async fn reorder_tabs(
+    State(st): State<AppState>,
+    session: Session,
+    Path(group_id): Path<String>,
+    Json(payload): Json<ReorderGroupTabsRequest>,
+) -> Result<Json<TabGroupWithTabs>, AppError> {
+    let conn = st.conn().await?;
+    let group =
+        groups::reorder_group_tabs(conn, session.user_id, group_id, payload.tab_ids).await?;
+    Ok(Json(group))
+}
+
+async fn user_groups(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<Vec<TabGroup>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let groups = groups::get_user_groups(conn, session.user_id).await?;
+    Ok(Json(groups))
+}
+
+async fn reorder_groups(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+    Json(payload): Json<ReorderGroupsRequest>,
+) -> Result<Json<Vec<TabGroup>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let groups = groups::reorder_groups(conn, session.user_id, payload.group_ids).await?;
+    Ok(Json(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{sessions, tabs, test_util::bulk_create_tabs, users},
+        metadata::FakeMetadataFetcher,
+        models::{group::NewGroupTab, tab::Tab, user::NewConfirmedUser},
+        routes::_test_utils::test_app,
+        types::{test_pool_from_env, Claims},
+    };
+    use deadpool_diesel::postgres::Pool;
+    use fake::{Fake, Faker};
+    use http::header;
+
+    fn group_tab(url: &str) -> NewGroupTab {
+        NewGroupTab {
+            url: url.to_string(),
+            notes: None,
+            title: None,
+        }
+    }
+
+    async fn mk_group(
+        pool: &Pool,
+        user_id: &str,
+        name: &str,
+        urls: &[&str],
+    ) -> anyhow::Result<TabGroupWithTabs> {
+        let group = groups::new_group(
+            pool.clone(),
+            &FakeMetadataFetcher,
+            NewTabGroup {
+                user_id: user_id.to_string(),
+                name: name.to_string(),
+                tabs: urls.iter().map(|u| group_tab(u)).collect(),
+            },
+        )
+        .await?;
+        Ok(group)
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_group_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let existing = bulk_create_tabs(c, user_id.clone(), 1).await?.remove(0);
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let payload = NewTabGroup {
+            user_id: user_id.clone(),
+            name: "research".into(),
+            tabs: vec![
+                group_tab("https://example.com/a"),
+                group_tab(&existing.url),
+                // a repeat of the first url is dropped
+                group_tab("https://example.com/a/"),
+                group_tab("https://example.com/b"),
+            ],
+        };
+        let response = server
+            .post("/groups")
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&payload)
+            .await;
+        let too_many = server
+            .post("/groups")
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&NewTabGroup {
+                user_id: user_id.clone(),
+                name: "too many".into(),
+                tabs: (0..=MAX_GROUP_TABS)
+                    .map(|i| group_tab(&format!("https://example.com/{}", i)))
+                    .collect(),
+            })
+            .await;
+        let c = pool.get().await?;
+        let all_tabs = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        response.assert_status(StatusCode::CREATED);
+        let group = response.json::<TabGroupWithTabs>();
+        assert_eq!(group.group.name, "research");
+        assert_eq!(group.group.user_id, user_id);
+        let urls: Vec<&str> = group.tabs.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/a",
+                &existing.url,
+                "https://example.com/b"
+            ]
+        );
+        assert_eq!(group.tabs[1].id, existing.id);
+        assert!(group
+            .tabs
+            .iter()
+            .enumerate()
+            .all(|(i, t)| t.group_id.as_ref() == Some(&group.group.id)
+                && t.group_position == Some(i as i32)));
+        // the existing tab was reused rather than duplicated
+        assert_eq!(all_tabs, 3);
+        too_many.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_group_wrong_user() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let payload = NewTabGroup {
+            user_id: "someone else".into(),
+            name: "research".into(),
+            tabs: vec![group_tab("https://example.com/a")],
+        };
+        let response = server
+            .post("/groups")
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&payload)
+            .await;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+
+        response.assert_status(StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_and_reorder_groups() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let first = mk_group(&pool, &user_id, "first", &["https://example.com/1"]).await?;
+        let second = mk_group(&pool, &user_id, "second", &["https://example.com/2"]).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let listed = server
+            .get(&format!("/users/{}/groups", user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let reordered = server
+            .put(&format!("/users/{}/groups", user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&ReorderGroupsRequest {
+                group_ids: vec![second.group.id.clone(), first.group.id.clone()],
+            })
+            .await;
+        let incomplete = server
+            .put(&format!("/users/{}/groups", user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&ReorderGroupsRequest {
+                group_ids: vec![second.group.id.clone()],
+            })
+            .await;
+        let wrong_user = server
+            .get("/users/someone-else/groups")
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+
+        listed.assert_status_ok();
+        let names: Vec<String> = listed
+            .json::<Vec<TabGroup>>()
+            .into_iter()
+            .map(|g| g.name)
+            .collect();
+        assert_eq!(names, vec!["first", "second"]);
+        reordered.assert_status_ok();
+        let reordered = reordered.json::<Vec<TabGroup>>();
+        assert_eq!(reordered[0].id, second.group.id);
+        assert_eq!(reordered[0].position, 0);
+        assert_eq!(reordered[1].id, first.group.id);
+        assert_eq!(reordered[1].position, 1);
+        incomplete.assert_status(StatusCode::BAD_REQUEST);
+        wrong_user.assert_status(StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rename_group() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let group = mk_group(&pool, &user_id, "before", &["https://example.com/1"]).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let renamed = server
+            .patch(&format!("/groups/{}", group.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&TabGroupUpdate {
+                name: "after".into(),
+            })
+            .await;
+        let blank = server
+            .patch(&format!("/groups/{}", group.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&TabGroupUpdate { name: " ".into() })
+            .await;
+        let missing = server
+            .patch("/groups/not-a-group")
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&TabGroupUpdate {
+                name: "after".into(),
+            })
+            .await;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+
+        renamed.assert_status_ok();
+        assert_eq!(renamed.json::<TabGroup>().name, "after");
+        blank.assert_status(StatusCode::BAD_REQUEST);
+        missing.assert_status(StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_reorder_group_tabs() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let group = mk_group(
+            &pool,
+            &user_id,
+            "window",
+            &[
+                "https://example.com/1",
+                "https://example.com/2",
+                "https://example.com/3",
+            ],
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let mut tab_ids: Vec<String> = group.tabs.iter().map(|t| t.id.clone()).collect();
+        tab_ids.reverse();
+        let reordered = server
+            .put(&format!("/groups/{}/tabs", group.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&ReorderGroupTabsRequest {
+                tab_ids: tab_ids.clone(),
+            })
+            .await;
+        let repeated = server
+            .put(&format!("/groups/{}/tabs", group.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .json(&ReorderGroupTabsRequest {
+                tab_ids: vec![tab_ids[0].clone(), tab_ids[0].clone(), tab_ids[1].clone()],
+            })
+            .await;
+        let fetched = server
+            .get(&format!("/groups/{}", group.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+
+        reordered.assert_status_ok();
+        let reordered_ids: Vec<String> = reordered
+            .json::<TabGroupWithTabs>()
+            .tabs
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(reordered_ids, tab_ids);
+        repeated.assert_status(StatusCode::BAD_REQUEST);
+        fetched.assert_status_ok();
+        let fetched_ids: Vec<String> = fetched
+            .json::<TabGroupWithTabs>()
+            .tabs
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(fetched_ids, tab_ids);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_group() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(groups_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let kept = mk_group(&pool, &user_id, "kept", &["https://example.com/kept"]).await?;
+        let trashed =
+            mk_group(&pool, &user_id, "trashed", &["https://example.com/trashed"]).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let ungrouped = server
+            .delete(&format!("/groups/{}", kept.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let with_tabs = server
+            .delete(&format!("/groups/{}", trashed.group.id))
+            .add_query_params(DeleteGroupRequest {
+                delete_tabs: Some(true),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let again = server
+            .delete(&format!("/groups/{}", kept.group.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let kept_tab = tabs::get_tab(c, user_id.clone(), kept.tabs[0].id.clone()).await?;
+        let c = pool.get().await?;
+        let trash: Vec<Tab> = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let remaining = groups::get_user_groups(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id).await?;
+
+        ungrouped.assert_status_ok();
+        with_tabs.assert_status_ok();
+        again.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(kept_tab.group_id, None);
+        assert_eq!(kept_tab.group_position, None);
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, trashed.tabs[0].id);
+        assert!(remaining.is_empty());
+        Ok(())
+    }
+}
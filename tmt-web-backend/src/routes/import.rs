@@ -0,0 +1,304 @@
+use crate::{
+    bookmarks::parse_bookmarks,
+    db::import,
+    models::{
+        session::Session,
+        tab::{ImportReport, ImportRequest},
+    },
+    types::{AppError, AppState},
+};
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+
+// browser exports with years of bookmarks run to several megabytes
+const IMPORT_BODY_LIMIT: usize = 20 * 1024 * 1024;
+
+pub fn import_router() -> Router<AppState> {
+    Router::new().route(
+        "/users/:user_id/import",
+        post(import_bookmarks).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+    )
+}
+
+async fn import_bookmarks(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+    Query(ImportRequest { dry_run }): Query<ImportRequest>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let bookmarks = tokio::task::spawn_blocking(move || parse_bookmarks(&body))
+        .await
+        .map_err(|e| {
+            tracing::error!("error parsing bookmarks: {:?}", e);
+            AppError::InternalServerError
+        })?;
+    if bookmarks.is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    let conn = st.conn().await?;
+    let report: ImportReport =
+        import::import_bookmarks(conn, session.user_id, bookmarks, dry_run).await?;
+    let status = if dry_run {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((status, Json(report)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{sessions, tabs, tags, test_util::bulk_create_tags_from_strings, users},
+        metadata::FakeMetadataFetcher,
+        models::{
+            tab::{NewTab, Tab},
+            user::NewConfirmedUser,
+        },
+        routes::_test_utils::test_app,
+        types::{test_pool_from_env, Claims, PaginationRequest, TabFilter},
+    };
+    use fake::{Fake, Faker};
+    use http::header;
+
+    const EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<DL><p>
+    <DT><H3>Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1712345678">Rust</A>
+        <DD>the language
+        <DT><H3>lang</H3>
+        <DL><p>
+            <DT><A HREF="https://www.rust-lang.org/#top">Rust again</A>
+            <DT><A HREF="https://go.dev" ADD_DATE="1712345678" TAGS="go">Go</A>
+        </DL><p>
+    </DL><p>
+    <DT><H3></H3>
+    <DL><p>
+        <DT><A HREF="https://example.org/">Unnamed folder</A>
+    </DL><p>
+    <DT><A HREF="https://example.com/?utm_source=feed">Example</A>
+    <DT><A HREF="javascript:alert(1)">bookmarklet</A>
+</DL><p>
+"#;
+
+    #[test_log::test(tokio::test)]
+    async fn test_import_dry_run() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(import_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: "https://example.com".into(),
+            notes: None,
+            title: None,
+        };
+        let existing = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
+        let c = pool.get().await?;
+        bulk_create_tags_from_strings(c, user_id.clone(), vec!["lang".into()]).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/users/{}/import", &user_id))
+            .add_query_params(ImportRequest {
+                dry_run: Some(true),
+            })
+            .text(EXPORT)
+            .add_header(header_name, header_value)
+            .await;
+
+        let user_tabs = tabs::get_user_tabs(
+            pool.clone(),
+            user_id.clone(),
+            TabFilter::default(),
+            PaginationRequest {
+                page: None,
+                page_size: None,
+                cursor: None,
+            },
+        )
+        .await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let report = resp.json::<ImportReport>();
+        assert!(report.dry_run);
+        assert_eq!(report.new_tabs, 3);
+        // "lang" already exists, the unnamed folder isn't one
+        assert_eq!(report.new_tags, 2);
+        assert_eq!(report.duplicates.len(), 2);
+        assert_eq!(report.duplicates[0].url, "https://www.rust-lang.org/#top");
+        assert_eq!(report.duplicates[0].existing_tab_id, None);
+        assert_eq!(
+            report.duplicates[1].url,
+            "https://example.com/?utm_source=feed"
+        );
+        assert_eq!(report.duplicates[1].existing_tab_id, Some(existing.id));
+        assert_eq!(report.skipped, vec!["javascript:alert(1)".to_string()]);
+        assert_eq!(user_tabs.results.len(), 1);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_import_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(import_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let existing_tag = bulk_create_tags_from_strings(c, user_id.clone(), vec!["lang".into()])
+            .await?
+            .remove(0);
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/users/{}/import", &user_id))
+            .text(EXPORT)
+            .add_header(header_name, header_value)
+            .await;
+
+        let user_tabs = tabs::get_user_tabs(
+            pool.clone(),
+            user_id.clone(),
+            TabFilter::default(),
+            PaginationRequest {
+                page: None,
+                page_size: None,
+                cursor: None,
+            },
+        )
+        .await?
+        .results;
+        let rust_tab: &Tab = user_tabs
+            .iter()
+            .find(|t| t.url == "https://www.rust-lang.org/")
+            .unwrap();
+        let rust_tab =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), rust_tab.id.clone()).await?;
+        let unnamed_tab: &Tab = user_tabs
+            .iter()
+            .find(|t| t.url == "https://example.org/")
+            .unwrap();
+        let unnamed_tab =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), unnamed_tab.id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::CREATED);
+        let report = resp.json::<ImportReport>();
+        assert!(!report.dry_run);
+        assert_eq!(report.new_tabs, 4);
+        assert_eq!(report.new_tags, 2);
+        assert_eq!(user_tabs.len(), 4);
+        assert_eq!(rust_tab.tab.title, Some("Rust".into()));
+        assert_eq!(rust_tab.tab.notes, Some("the language".into()));
+        assert_eq!(
+            rust_tab.tab.created_at,
+            chrono::DateTime::from_timestamp(1712345678, 0)
+                .unwrap()
+                .naive_utc()
+        );
+        let mut tag_names: Vec<String> = rust_tab.tags.iter().map(|t| t.tag.clone()).collect();
+        tag_names.sort();
+        assert_eq!(tag_names, vec!["Bookmarks bar", "lang"]);
+        assert!(rust_tab.tags.iter().any(|t| t.id == existing_tag.id));
+        assert!(unnamed_tab.tags.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_import_not_bookmarks() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(import_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/users/{}/import", &user_id))
+            .text("just some text")
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_import_wrong_user_id() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(import_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/users/{}/import", Faker.fake::<String>()))
+            .text(EXPORT)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_forbidden();
+        Ok(())
+    }
+}
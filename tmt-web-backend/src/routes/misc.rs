@@ -69,7 +69,7 @@ mod test {
         let user = new_user_confirmed(c, create_user_data).await?;
 
         let sp = pool.clone();
-        let session = new_session(sp, user.email.clone()).await?;
+        let session = new_session(sp, user.email.clone(), Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
 
         let bearer = format!("Bearer {}", token);
@@ -1,17 +1,20 @@
 use crate::{
-    db::{tabs, tags},
+    db::tabs,
     models::{
         session::Session,
-        tab::{NewTab, NewTabTag, NewTabWithTags, Tab, TabWithTags},
-        tag::{NewTag, Tag},
+        tab::{
+            BatchTabOp, BatchTabsRequest, BatchTabsResponse, DeleteTabsRequest, MergeTabsRequest,
+            NewTab, NewTabWithTags, Tab, TabChangeset, TabStatusUpdate, TabUpdate, TabWithTags,
+            TabsDeletedResponse,
+        },
     },
-    types::{AppError, AppState, PaginatedResult, PaginationRequest},
+    types::{AppError, AppState, PaginatedResult, TabFilter, TabListRequest, TabSearchRequest},
 };
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{get, post, put},
     Json, Router,
 };
 
@@ -19,9 +22,17 @@ pub fn tabs_router() -> Router<AppState> {
     Router::new()
         .route("/tabs", post(create))
         .route("/tabs/with-tags", post(create_with_tags))
-        .route("/tabs/:tab_id", get(get_tab))
+        .route("/tabs/delete", post(delete_tabs))
+        .route("/tabs/batch", post(batch_tabs))
+        .route(
+            "/tabs/:tab_id",
+            get(get_tab).patch(update_tab).delete(delete_tab),
+        )
         .route("/tabs/:tab_id/with-tags", get(get_tab_with_tags))
+        .route("/tabs/:tab_id/merge", post(merge_tab))
+        .route("/tabs/:tab_id/status", put(set_tab_status))
         .route("/users/:user_id/tabs", get(user_tabs))
+        .route("/users/:user_id/tabs/search", get(search_user_tabs))
 }
 
 async fn create(
@@ -32,8 +43,7 @@ async fn create(
     if payload.user_id != session.user_id {
         Err(AppError::WrongCredentials)
     } else {
-        let conn = st.conn().await?;
-        let tab = tabs::new_tab(conn, payload).await?;
+        let tab = tabs::new_tab(st.pool(), st.fetcher(), payload).await?;
         Ok((StatusCode::CREATED, Json(tab)))
     }
 }
@@ -46,46 +56,8 @@ async fn create_with_tags(
     if payload.tab.user_id != session.user_id {
         return Err(AppError::WrongCredentials);
     }
-    let conn = st.conn().await?;
-    let tab = tabs::new_tab(conn, payload.tab).await?;
-    let tab_id = tab.id.clone();
-    let tags = payload.tags;
-    let mut new: Vec<NewTag> = Vec::with_capacity(tags.len());
-    let mut to_insert: Vec<NewTabTag> = Vec::with_capacity(tags.len());
-    let mut tags_to_return: Vec<Tag> = Vec::with_capacity(tags.len());
-    for tag in tags {
-        if let Some(gid) = tag.id {
-            to_insert.push(NewTabTag {
-                tab_id: tab_id.clone(),
-                tag_id: gid.clone(),
-            });
-            tags_to_return.push(Tag {
-                id: gid,
-                user_id: session.user_id.clone(),
-                tag: tag.tag,
-            })
-        } else {
-            new.push(NewTag {
-                user_id: tag.user_id,
-                tag: tag.tag,
-            });
-        }
-    }
-    let conn = st.conn().await?;
-    let new_tags = tags::bulk_insert_tags(conn, new).await?;
-    for tag in new_tags {
-        to_insert.push(NewTabTag {
-            tab_id: tab_id.clone(),
-            tag_id: tag.id.clone(),
-        });
-        tags_to_return.push(tag);
-    }
-    let conn = st.conn().await?;
-    tags::bulk_mk_tab_tags(conn, to_insert).await?;
-    let tab_with_tags = TabWithTags {
-        tab,
-        tags: tags_to_return,
-    };
+    let tab_with_tags =
+        tabs::new_tab_with_tags(st.pool(), st.fetcher(), session.user_id, payload).await?;
     Ok((StatusCode::CREATED, Json(tab_with_tags)))
 }
 
@@ -99,6 +71,99 @@ async fn get_tab(
     Ok(Json(tab))
 }
 
+async fn update_tab(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tab_id): Path<String>,
+    Json(payload): Json<TabUpdate>,
+) -> Result<Json<Tab>, AppError> {
+    if payload.is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let changes = TabChangeset::try_from(payload)?;
+    let tab = tabs::update_tab(st.pool(), st.fetcher(), session.user_id, tab_id, changes).await?;
+    Ok(Json(tab))
+}
+
+async fn set_tab_status(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tab_id): Path<String>,
+    Json(TabStatusUpdate { status }): Json<TabStatusUpdate>,
+) -> Result<Json<Tab>, AppError> {
+    let conn = st.conn().await?;
+    let tab = tabs::set_tab_status(conn, session.user_id, tab_id, status).await?;
+    Ok(Json(tab))
+}
+
+async fn delete_tab(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tab_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let conn = st.conn().await?;
+    let deleted = tabs::delete_tab(conn, session.user_id, tab_id).await?;
+    if deleted == 0 {
+        Err(AppError::NotFound)
+    } else {
+        Ok(StatusCode::OK)
+    }
+}
+
+async fn delete_tabs(
+    State(st): State<AppState>,
+    session: Session,
+    Json(DeleteTabsRequest { tab_ids }): Json<DeleteTabsRequest>,
+) -> Result<Json<TabsDeletedResponse>, AppError> {
+    if tab_ids.is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let conn = st.conn().await?;
+    let deleted = tabs::delete_tabs(conn, session.user_id, tab_ids).await?;
+    if deleted == 0 {
+        Err(AppError::NotFound)
+    } else {
+        Ok(Json(TabsDeletedResponse { deleted }))
+    }
+}
+
+// keeps a single transaction from locking too many rows
+const MAX_BATCH_SIZE: usize = 500;
+// tabs × tags rows get inserted at once, at two bind parameters each; Postgres allows 65535
+const MAX_BATCH_TAGS: usize = 50;
+
+async fn batch_tabs(
+    State(st): State<AppState>,
+    session: Session,
+    Json(payload): Json<BatchTabsRequest>,
+) -> Result<Json<BatchTabsResponse>, AppError> {
+    if payload.tab_ids.is_empty() || payload.tab_ids.len() > MAX_BATCH_SIZE {
+        return Err(AppError::BadRequest);
+    }
+    if let BatchTabOp::AddTags { tag_ids } | BatchTabOp::RemoveTags { tag_ids } = &payload.op {
+        if tag_ids.is_empty() || tag_ids.len() > MAX_BATCH_TAGS {
+            return Err(AppError::BadRequest);
+        }
+    }
+    let conn = st.conn().await?;
+    let results = tabs::batch_update_tabs(conn, session.user_id, payload).await?;
+    Ok(Json(results))
+}
+
+async fn merge_tab(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tab_id): Path<String>,
+    Json(MergeTabsRequest { source_tab_id }): Json<MergeTabsRequest>,
+) -> Result<Json<TabWithTags>, AppError> {
+    if tab_id == source_tab_id {
+        return Err(AppError::BadRequest);
+    }
+    let conn = st.conn().await?;
+    let merged = tabs::merge_tabs(conn, session.user_id, tab_id, source_tab_id).await?;
+    Ok(Json(merged))
+}
+
 async fn get_tab_with_tags(
     State(st): State<AppState>,
     session: Session,
@@ -113,13 +178,32 @@ async fn user_tabs(
     State(st): State<AppState>,
     session: Session,
     Path(user_id): Path<String>,
-    Query(pr): Query<PaginationRequest>,
+    Query(lr): Query<TabListRequest>,
+) -> Result<Json<PaginatedResult<Tab>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let pool = st.pool();
+    let filter = TabFilter::try_from(&lr)?;
+    let tabs = tabs::get_user_tabs(pool, session.user_id.clone(), filter, lr.pagination()).await?;
+    Ok(Json(tabs))
+}
+
+async fn search_user_tabs(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+    Query(sr): Query<TabSearchRequest>,
 ) -> Result<Json<PaginatedResult<Tab>>, AppError> {
     if user_id != session.user_id {
         return Err(AppError::WrongCredentials);
     }
+    if sr.q.trim().is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let pr = sr.pagination();
     let pool = st.pool();
-    let tabs = tabs::get_user_tabs(pool, session.user_id.clone(), pr).await?;
+    let tabs = tabs::search_user_tabs(pool, session.user_id.clone(), sr.q, pr).await?;
     Ok(Json(tabs))
 }
 
@@ -128,14 +212,21 @@ mod tests {
     use super::*;
     use crate::{
         db::{
-            sessions,
+            sessions, tags,
             tags::bulk_mk_tab_tags,
-            test_util::{bulk_create_tabs, bulk_create_tags},
+            test_util::{bulk_create_tabs, bulk_create_tags, bulk_create_tags_from_strings},
             users,
         },
-        models::{tag::MaybeNewTag, user::NewConfirmedUser},
+        metadata::FakeMetadataFetcher,
+        models::{
+            tab::{BatchTabOutcome, NewTabTag, TabStatus},
+            tag::{MaybeNewTag, Tag},
+            user::NewConfirmedUser,
+        },
         routes::_test_utils::test_app,
-        types::{test_pool_from_env, Claims},
+        types::{
+            test_pool_from_env, Claims, PaginationRequest, SortDirection, TabSort, TagMatchMode,
+        },
     };
     use fake::{Fake, Faker};
     use http::header;
@@ -151,7 +242,7 @@ mod tests {
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_email = user.email.clone();
         let user_id = user.id.clone();
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -162,6 +253,7 @@ mod tests {
             user_id: user_id.clone(),
             url: url.clone(),
             notes: notes.clone(),
+            title: None,
         };
         let resp = server
             .post("/tabs")
@@ -179,6 +271,50 @@ mod tests {
         assert_eq!(tab.user_id, user_id);
         assert_eq!(tab.url, url);
         assert_eq!(tab.notes, notes);
+        assert_eq!(tab.title, Some(format!("Title of {}", url)));
+        assert_eq!(tab.description, Some(format!("Description of {}", url)));
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_tab_with_title() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let url = String::from("https://example.com");
+        let title: Option<String> = Some("my title".into());
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: url.clone(),
+            notes: None,
+            title: title.clone(),
+        };
+        let resp = server
+            .post("/tabs")
+            .json(&tab_data)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::CREATED);
+        let tab = resp.json::<Tab>();
+        assert_eq!(tab.title, title);
+        assert_eq!(tab.description, Some(format!("Description of {}", url)));
         Ok(())
     }
 
@@ -193,7 +329,7 @@ mod tests {
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_id = user.id.clone();
         let user_email = user.email.clone();
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -205,6 +341,7 @@ mod tests {
             user_id: other_user_id,
             url: url.clone(),
             notes: notes.clone(),
+            title: None,
         };
         let resp = server
             .post("/tabs")
@@ -238,11 +375,11 @@ mod tests {
             user_id: user_id.clone(),
             url: url.clone(),
             notes: notes.clone(),
+            title: None,
         };
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let tab_id = tab.id.clone();
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -284,10 +421,10 @@ mod tests {
             user_id: user_id.clone(),
             url: url.clone(),
             notes: notes.clone(),
+            title: None,
         };
-        let c = pool.get().await?;
-        let _tab = tabs::new_tab(c, tab_data).await?;
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let _tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -331,12 +468,12 @@ mod tests {
             user_id: u1_id.clone(),
             url: url.clone(),
             notes: notes.clone(),
+            title: None,
         };
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
 
         // "log in" as other user
-        let session = sessions::new_session(pool.clone(), u2_email).await?;
+        let session = sessions::new_session(pool.clone(), u2_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -358,30 +495,38 @@ mod tests {
     }
 
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_ok() -> anyhow::Result<()> {
+    async fn test_update_tab_ok() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
         let mut user_data = Faker.fake::<NewConfirmedUser>();
         user_data.confirmed = true;
-        let c = pool.get().await?;
         let user = users::new_user_confirmed(c, user_data).await?;
-        let user_id = user.id.clone();
         let user_email = user.email.clone();
-        let c = pool.get().await?;
-        let tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
-
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let user_id = user.id.clone();
+        let url = String::from("https://example.com");
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: url.clone(),
+            notes: Some("nots".into()),
+            title: None,
+        };
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: Some(1),
-            page_size: Some(5),
+        let notes: Option<String> = Some("notes".into());
+        let update = TabUpdate {
+            url: None,
+            notes: Some(notes.clone()),
+            title: None,
         };
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .patch(&format!("/tabs/{}", tab.id))
+            .json(&update)
             .add_header(header_name, header_value)
             .await;
 
@@ -391,37 +536,71 @@ mod tests {
         let _ = users::deconfirm_user(c, user_id.clone()).await?;
 
         resp.assert_status_ok();
-        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
-        assert!(!paginated_tabs.has_more);
-        let gotten_tabs = paginated_tabs.results;
-        assert_eq!(gotten_tabs, tabs);
+        let updated = resp.json::<Tab>();
+        assert_eq!(updated.id, tab.id);
+        assert_eq!(updated.url, url);
+        assert_eq!(updated.notes, notes);
+        assert_eq!(updated.created_at, tab.created_at);
+        assert!(updated.modified_at > tab.modified_at);
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_pagination_more() -> anyhow::Result<()> {
+    async fn test_update_tab_url() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
         let mut user_data = Faker.fake::<NewConfirmedUser>();
         user_data.confirmed = true;
-        let c = pool.get().await?;
         let user = users::new_user_confirmed(c, user_data).await?;
-        let user_id = user.id.clone();
         let user_email = user.email.clone();
-        let c = pool.get().await?;
-        let tabs = bulk_create_tabs(c, user_id.clone(), 47).await?;
-
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let user_id = user.id.clone();
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: "https://example.com/old".into(),
+            notes: None,
+            title: None,
+        };
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: Some(8),
-            page_size: Some(5),
+        let mut invalid = Vec::with_capacity(3);
+        for url in ["", "   ", "ftp://example.com/file"] {
+            let update = TabUpdate {
+                url: Some(url.into()),
+                notes: None,
+                title: None,
+            };
+            let resp = server
+                .patch(&format!("/tabs/{}", tab.id))
+                .json(&update)
+                .add_header(header_name.clone(), header_value.clone())
+                .await;
+            invalid.push(resp.status_code());
+        }
+        let new_url = "https://example.com/new";
+        let update = TabUpdate {
+            url: Some(new_url.into()),
+            notes: None,
+            title: None,
         };
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .patch(&format!("/tabs/{}", tab.id))
+            .json(&update)
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let update = TabUpdate {
+            url: Some("https://example.com/titled".into()),
+            notes: None,
+            title: Some(Some("my title".into())),
+        };
+        let titled = server
+            .patch(&format!("/tabs/{}", tab.id))
+            .json(&update)
             .add_header(header_name, header_value)
             .await;
 
@@ -430,80 +609,110 @@ mod tests {
         let c = pool.get().await?;
         let _ = users::deconfirm_user(c, user_id.clone()).await?;
 
+        assert_eq!(invalid, vec![StatusCode::BAD_REQUEST; 3]);
         resp.assert_status_ok();
-        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
-        let offset_tabs = tabs.into_iter().skip(35).take(5).collect::<Vec<_>>();
-        assert!(paginated_tabs.has_more);
-        let gotten_tabs = paginated_tabs.results;
-        assert_eq!(gotten_tabs, offset_tabs);
+        let updated = resp.json::<Tab>();
+        assert_eq!(updated.url, new_url);
+        // the old page's metadata is replaced with the new one's
+        assert_eq!(updated.title, Some(format!("Title of {}", new_url)));
+        assert_eq!(
+            updated.description,
+            Some(format!("Description of {}", new_url))
+        );
+        titled.assert_status_ok();
+        let titled = titled.json::<Tab>();
+        assert_eq!(titled.title, Some("my title".into()));
+        assert_eq!(
+            titled.description,
+            Some("Description of https://example.com/titled".into())
+        );
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_pagination_no_more() -> anyhow::Result<()> {
+    async fn test_update_tab_wrong_user_id() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
-        let mut user_data = Faker.fake::<NewConfirmedUser>();
-        user_data.confirmed = true;
         let c = pool.get().await?;
-        let user = users::new_user_confirmed(c, user_data).await?;
-        let user_id = user.id.clone();
-        let user_email = user.email.clone();
+
+        let mut u1_data = Faker.fake::<NewConfirmedUser>();
+        u1_data.confirmed = true;
+        let u1 = users::new_user_confirmed(c, u1_data).await?;
+        let u1_id = u1.id.clone();
+        let mut u2_data = Faker.fake::<NewConfirmedUser>();
+        u2_data.confirmed = true;
         let c = pool.get().await?;
-        let tabs = bulk_create_tabs(c, user_id.clone(), 40).await?;
+        let u2 = users::new_user_confirmed(c, u2_data).await?;
+        let u2_id = u2.id.clone();
+        let u2_email = u2.email.clone();
+
+        let notes: Option<String> = Some("notes".into());
+        let tab_data = NewTab {
+            user_id: u1_id.clone(),
+            url: String::from("https://example.com"),
+            notes: notes.clone(),
+            title: None,
+        };
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), u2_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: Some(8),
-            page_size: Some(5),
+        let update = TabUpdate {
+            url: None,
+            notes: Some(None),
+            title: None,
         };
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .patch(&format!("/tabs/{}", tab.id))
+            .json(&update)
             .add_header(header_name, header_value)
             .await;
 
         let c = pool.get().await?;
-        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let unchanged = tabs::get_tab(c, u1_id.clone(), tab.id.clone()).await?;
+
         let c = pool.get().await?;
-        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+        let _ = tabs::delete_user_tabs(c, u1_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, u1_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, u2_id.clone()).await?;
 
-        resp.assert_status_ok();
-        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
-        let offset_tabs = tabs.into_iter().skip(35).take(5).collect::<Vec<_>>();
-        assert!(!paginated_tabs.has_more);
-        let gotten_tabs = paginated_tabs.results;
-        assert_eq!(gotten_tabs, offset_tabs);
+        resp.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(unchanged.notes, notes);
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_past_end() -> anyhow::Result<()> {
+    async fn test_update_tab_no_changes() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
         let mut user_data = Faker.fake::<NewConfirmedUser>();
         user_data.confirmed = true;
-        let c = pool.get().await?;
         let user = users::new_user_confirmed(c, user_data).await?;
-        let user_id = user.id.clone();
         let user_email = user.email.clone();
+        let user_id = user.id.clone();
         let c = pool.get().await?;
-        let _tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
-
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 1).await?;
+        let tab = tabs.first().unwrap().clone();
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: Some(2),
-            page_size: Some(5),
+        let update = TabUpdate {
+            url: None,
+            notes: None,
+            title: None,
         };
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .patch(&format!("/tabs/{}", tab.id))
+            .json(&update)
             .add_header(header_name, header_value)
             .await;
 
@@ -512,49 +721,1481 @@ mod tests {
         let c = pool.get().await?;
         let _ = users::deconfirm_user(c, user_id.clone()).await?;
 
-        resp.assert_status_ok();
-        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
-        assert!(!paginated_tabs.has_more);
-        assert!(paginated_tabs.results.is_empty());
+        resp.assert_status(StatusCode::BAD_REQUEST);
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_no_tabs() -> anyhow::Result<()> {
+    async fn test_create_tab_duplicate() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
         let mut user_data = Faker.fake::<NewConfirmedUser>();
         user_data.confirmed = true;
-        let c = pool.get().await?;
         let user = users::new_user_confirmed(c, user_data).await?;
-        let user_id = user.id.clone();
         let user_email = user.email.clone();
-
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let user_id = user.id.clone();
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: "https://example.com/a/?utm_source=newsletter".into(),
+            notes: None,
+            title: None,
+        };
+        let existing = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: Some(2),
-            page_size: Some(5),
+        let tab_data = NewTab {
+            user_id: user_id.clone(),
+            url: "https://EXAMPLE.com/a#comments".into(),
+            notes: None,
+            title: None,
         };
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .post("/tabs")
+            .json(&tab_data)
             .add_header(header_name, header_value)
             .await;
 
         let c = pool.get().await?;
-        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
 
-        resp.assert_status_ok();
+        resp.assert_status(StatusCode::CONFLICT);
+        let body = resp.json::<serde_json::Value>();
+        assert_eq!(body["id"], existing.id);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_update_tab_duplicate() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let mut existing = Vec::with_capacity(2);
+        for url in ["https://example.com/a", "https://example.com/b"] {
+            let tab_data = NewTab {
+                user_id: user_id.clone(),
+                url: url.into(),
+                notes: None,
+                title: None,
+            };
+            existing.push(tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?);
+        }
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let update = TabUpdate {
+            url: Some(existing[0].url.clone()),
+            notes: None,
+            title: None,
+        };
+        let resp = server
+            .patch(&format!("/tabs/{}", existing[1].id))
+            .json(&update)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::CONFLICT);
+        let body = resp.json::<serde_json::Value>();
+        assert_eq!(body["id"], existing[0].id);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_tab_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 2).await?;
+        let tab = tabs.first().unwrap().clone();
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .delete(&format!("/tabs/{}", tab.id))
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let gotten = tabs::get_tab(c, user_id.clone(), tab.id.clone()).await;
+        let c = pool.get().await?;
+        let trashed = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        assert!(matches!(gotten, Err(AppError::NotFound)));
+        assert_eq!(
+            trashed,
+            vec![Tab {
+                deleted_at: trashed[0].deleted_at,
+                ..tab
+            }]
+        );
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_tab_doesnt_belong() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut u1_data = Faker.fake::<NewConfirmedUser>();
+        u1_data.confirmed = true;
+        let u1 = users::new_user_confirmed(c, u1_data).await?;
+        let u1_id = u1.id.clone();
+        let mut u2_data = Faker.fake::<NewConfirmedUser>();
+        u2_data.confirmed = true;
+        let c = pool.get().await?;
+        let u2 = users::new_user_confirmed(c, u2_data).await?;
+        let u2_id = u2.id.clone();
+        let u2_email = u2.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, u1_id.clone(), 1).await?;
+        let tab = tabs.first().unwrap().clone();
+
+        let session = sessions::new_session(pool.clone(), u2_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .delete(&format!("/tabs/{}", tab.id))
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let gotten = tabs::get_tab(c, u1_id.clone(), tab.id.clone()).await;
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, u1_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, u1_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, u2_id.clone()).await?;
+
+        resp.assert_status(StatusCode::NOT_FOUND);
+        assert!(gotten.is_ok());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_tabs_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+        let mut tab_ids: Vec<String> = tabs.iter().take(3).map(|t| t.id.clone()).collect();
+        tab_ids.push(Faker.fake::<String>());
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post("/tabs/delete")
+            .json(&DeleteTabsRequest { tab_ids })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let trashed = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        assert_eq!(resp.json::<TabsDeletedResponse>().deleted, 3);
+        assert_eq!(trashed.len(), 3);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_tabs_none_found() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let tab_ids = vec![Faker.fake::<String>(), Faker.fake::<String>()];
+        let resp = server
+            .post("/tabs/delete")
+            .json(&DeleteTabsRequest { tab_ids })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_batch_add_and_remove_tags() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags(c, user_id.clone(), 2).await?;
+        let c = pool.get().await?;
+        bulk_mk_tab_tags(
+            c,
+            vec![NewTabTag {
+                tab_id: user_tabs[0].id.clone(),
+                tag_id: user_tags[0].id.clone(),
+            }],
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let missing_id = Faker.fake::<String>();
+        let add_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![
+                    user_tabs[0].id.clone(),
+                    user_tabs[1].id.clone(),
+                    missing_id.clone(),
+                ],
+                op: BatchTabOp::AddTags {
+                    tag_ids: user_tags.iter().map(|t| t.id.clone()).collect(),
+                },
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let remove_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: user_tabs.iter().map(|t| t.id.clone()).collect(),
+                op: BatchTabOp::RemoveTags {
+                    tag_ids: vec![user_tags[0].id.clone()],
+                },
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let mut remaining = Vec::with_capacity(user_tabs.len());
+        for tab in user_tabs.iter() {
+            let tab =
+                tabs::get_tab_with_tags(pool.clone(), user_id.clone(), tab.id.clone()).await?;
+            remaining.push(tab.tags.into_iter().map(|t| t.id).collect::<Vec<String>>());
+        }
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        add_resp.assert_status_ok();
+        let outcomes: Vec<(String, BatchTabOutcome)> = add_resp
+            .json::<BatchTabsResponse>()
+            .results
+            .into_iter()
+            .map(|r| (r.tab_id, r.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (user_tabs[0].id.clone(), BatchTabOutcome::Updated),
+                (user_tabs[1].id.clone(), BatchTabOutcome::Updated),
+                (missing_id, BatchTabOutcome::NotFound),
+            ]
+        );
+        remove_resp.assert_status_ok();
+        let outcomes: Vec<BatchTabOutcome> = remove_resp
+            .json::<BatchTabsResponse>()
+            .results
+            .into_iter()
+            .map(|r| r.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                BatchTabOutcome::Updated,
+                BatchTabOutcome::Updated,
+                BatchTabOutcome::Unchanged,
+            ]
+        );
+        let tag_1 = vec![user_tags[1].id.clone()];
+        assert_eq!(remaining, vec![tag_1.clone(), tag_1, vec![]]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_batch_set_status_and_delete() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let mut other_data = Faker.fake::<NewConfirmedUser>();
+        other_data.confirmed = true;
+        let c = pool.get().await?;
+        let other = users::new_user_confirmed(c, other_data).await?;
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 2).await?;
+        let c = pool.get().await?;
+        let other_tab = bulk_create_tabs(c, other.id.clone(), 1).await?.remove(0);
+        let c = pool.get().await?;
+        tabs::set_tab_status(c, user_id.clone(), user_tabs[1].id.clone(), TabStatus::Read).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let status_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![
+                    user_tabs[0].id.clone(),
+                    user_tabs[1].id.clone(),
+                    other_tab.id.clone(),
+                ],
+                op: BatchTabOp::SetStatus {
+                    status: TabStatus::Read,
+                },
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let delete_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![user_tabs[0].id.clone(), other_tab.id.clone()],
+                op: BatchTabOp::Delete,
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let trashed = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let other_after = tabs::get_tab(c, other.id.clone(), other_tab.id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, other.id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, other.id.clone()).await?;
+
+        status_resp.assert_status_ok();
+        let outcomes: Vec<BatchTabOutcome> = status_resp
+            .json::<BatchTabsResponse>()
+            .results
+            .into_iter()
+            .map(|r| r.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                BatchTabOutcome::Updated,
+                BatchTabOutcome::Unchanged,
+                BatchTabOutcome::NotFound,
+            ]
+        );
+        delete_resp.assert_status_ok();
+        let outcomes: Vec<BatchTabOutcome> = delete_resp
+            .json::<BatchTabsResponse>()
+            .results
+            .into_iter()
+            .map(|r| r.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![BatchTabOutcome::Updated, BatchTabOutcome::NotFound]
+        );
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id, user_tabs[0].id);
+        assert_eq!(trashed[0].status, TabStatus::Read);
+        assert_eq!(other_after.status, TabStatus::Unread);
+        assert_eq!(other_after.deleted_at, None);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_batch_rejects_foreign_tags() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let mut other_data = Faker.fake::<NewConfirmedUser>();
+        other_data.confirmed = true;
+        let c = pool.get().await?;
+        let other = users::new_user_confirmed(c, other_data).await?;
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 1).await?;
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags(c, user_id.clone(), 1).await?;
+        let c = pool.get().await?;
+        let other_tags = bulk_create_tags(c, other.id.clone(), 1).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let foreign_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![user_tabs[0].id.clone()],
+                op: BatchTabOp::AddTags {
+                    tag_ids: vec![user_tags[0].id.clone(), other_tags[0].id.clone()],
+                },
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let empty_resp = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![],
+                op: BatchTabOp::Delete,
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let too_many_tags = server
+            .post("/tabs/batch")
+            .json(&BatchTabsRequest {
+                tab_ids: vec![user_tabs[0].id.clone()],
+                op: BatchTabOp::AddTags {
+                    tag_ids: (0..=MAX_BATCH_TAGS).map(|i| i.to_string()).collect(),
+                },
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let tab =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), user_tabs[0].id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, other.id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, other.id.clone()).await?;
+
+        foreign_resp.assert_status(StatusCode::FORBIDDEN);
+        // nothing was attached, not even the user's own tag
+        assert!(tab.tags.is_empty());
+        empty_resp.assert_status(StatusCode::BAD_REQUEST);
+        too_many_tags.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_merge_tabs_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let mut tab_data = Vec::with_capacity(2);
+        for (url, notes) in [
+            ("https://example.com/a", "first"),
+            ("https://example.com/b", "second"),
+        ] {
+            tab_data.push(NewTab {
+                user_id: user_id.clone(),
+                url: url.into(),
+                notes: Some(notes.into()),
+                title: None,
+            });
+        }
+        let c = pool.get().await?;
+        let to_merge = tabs::bulk_insert_tabs(c, tab_data).await?;
+        let target = &to_merge[0];
+        let source = &to_merge[1];
+        let c = pool.get().await?;
+        let tags = bulk_create_tags(c, user_id.clone(), 3).await?;
+        // target has tags 0 and 1, source has tags 1 and 2
+        let ntt = [(target, 0), (target, 1), (source, 1), (source, 2)]
+            .into_iter()
+            .map(|(tab, i)| NewTabTag {
+                tab_id: tab.id.clone(),
+                tag_id: tags[i].id.clone(),
+            })
+            .collect();
+        let c = pool.get().await?;
+        bulk_mk_tab_tags(c, ntt).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/tabs/{}/merge", target.id))
+            .json(&MergeTabsRequest {
+                source_tab_id: source.id.clone(),
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let source_after = tabs::get_tab(c, user_id.clone(), source.id.clone()).await;
+        let c = pool.get().await?;
+        let trashed = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let merged = resp.json::<TabWithTags>();
+        assert_eq!(merged.tab.id, target.id);
+        assert_eq!(merged.tab.notes, Some("first\n\nsecond".into()));
+        let mut merged_tag_ids: Vec<String> = merged.tags.into_iter().map(|t| t.id).collect();
+        merged_tag_ids.sort();
+        let mut expected_tag_ids: Vec<String> = tags.into_iter().map(|t| t.id).collect();
+        expected_tag_ids.sort();
+        assert_eq!(merged_tag_ids, expected_tag_ids);
+        assert!(matches!(source_after, Err(AppError::NotFound)));
+        // the source can still be restored from the trash
+        assert_eq!(
+            trashed.into_iter().map(|t| t.id).collect::<Vec<String>>(),
+            vec![source.id.clone()]
+        );
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_merge_tabs_doesnt_belong() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let c = pool.get().await?;
+
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_email = user.email.clone();
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let target = bulk_create_tabs(c, user_id.clone(), 1).await?.remove(0);
+
+        let c = pool.get().await?;
+        let mut other_user_data = Faker.fake::<NewConfirmedUser>();
+        other_user_data.confirmed = true;
+        let other_user = users::new_user_confirmed(c, other_user_data).await?;
+        let other_user_id = other_user.id.clone();
+        let c = pool.get().await?;
+        let source = bulk_create_tabs(c, other_user_id.clone(), 1)
+            .await?
+            .remove(0);
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/tabs/{}/merge", target.id))
+            .json(&MergeTabsRequest {
+                source_tab_id: source.id.clone(),
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let source_after = tabs::get_tab(c, other_user_id.clone(), source.id.clone()).await;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, other_user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, other_user_id.clone()).await?;
+
+        resp.assert_status_not_found();
+        assert!(source_after.is_ok());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(1),
+            page_size: Some(5),
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        assert!(!paginated_tabs.has_more);
+        let gotten_tabs = paginated_tabs.results;
+        assert_eq!(gotten_tabs, tabs);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_pagination_more() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 47).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(8),
+            page_size: Some(5),
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        let offset_tabs = tabs.into_iter().skip(35).take(5).collect::<Vec<_>>();
+        assert!(paginated_tabs.has_more);
+        let gotten_tabs = paginated_tabs.results;
+        assert_eq!(gotten_tabs, offset_tabs);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_pagination_no_more() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 40).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(8),
+            page_size: Some(5),
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        let offset_tabs = tabs.into_iter().skip(35).take(5).collect::<Vec<_>>();
+        assert!(!paginated_tabs.has_more);
+        let gotten_tabs = paginated_tabs.results;
+        assert_eq!(gotten_tabs, offset_tabs);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_past_end() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let _tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(2),
+            page_size: Some(5),
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        assert!(!paginated_tabs.has_more);
+        assert!(paginated_tabs.results.is_empty());
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_no_tabs() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(2),
+            page_size: Some(5),
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        assert!(!paginated_tabs.has_more);
+        assert!(paginated_tabs.results.is_empty());
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_defaults() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 50).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: None,
+            page_size: None,
+            cursor: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
+        let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
+        let expected_tabs = tabs.into_iter().take(25).collect::<Vec<_>>();
+        assert!(paginated_tabs.has_more);
+        assert_eq!(paginated_tabs.results, expected_tabs);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_wrong_user() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let _tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+
+        let mut login_user_data = Faker.fake::<NewConfirmedUser>();
+        login_user_data.confirmed = true;
+        let c = pool.get().await?;
+        let login_user = users::new_user_confirmed(c, login_user_data).await?;
+        let login_user_email = login_user.email.clone();
+
+        let session =
+            sessions::new_session(pool.clone(), login_user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let pag_info = PaginationRequest {
+            page: Some(1),
+            page_size: Some(5),
+            cursor: None,
+        };
+
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(pag_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_cursor() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let first_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(PaginationRequest {
+                page: None,
+                page_size: Some(3),
+                cursor: Some(String::new()),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let first_page = first_resp.json::<PaginatedResult<Tab>>();
+
+        // a tab created mid-scroll doesn't shift the next page
+        let c = pool.get().await?;
+        bulk_create_tabs(c, user_id.clone(), 1).await?;
+        let second_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(PaginationRequest {
+                page: None,
+                page_size: Some(3),
+                cursor: first_page.next_cursor.clone(),
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        first_resp.assert_status_ok();
+        assert!(first_page.has_more);
+        assert!(first_page.next_cursor.is_some());
+        assert_eq!(first_page.results, tabs[..3]);
+        second_resp.assert_status_ok();
+        let second_page = second_resp.json::<PaginatedResult<Tab>>();
+        assert!(!second_page.has_more);
+        assert!(second_page.next_cursor.is_none());
+        assert_eq!(second_page.results, tabs[3..]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_bad_cursor() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(PaginationRequest {
+                page: None,
+                page_size: None,
+                cursor: Some("not a cursor".into()),
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_sort_by_url() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = ["https://c.com", "https://a.com", "https://b.com"]
+            .into_iter()
+            .map(|url| NewTab {
+                user_id: user_id.clone(),
+                url: url.into(),
+                notes: None,
+                title: None,
+            })
+            .collect();
+        let c = pool.get().await?;
+        let tabs = tabs::bulk_insert_tabs(c, tab_data).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let first_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                page_size: Some(2),
+                cursor: Some(String::new()),
+                sort: Some(TabSort::Url),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let first_page = first_resp.json::<PaginatedResult<Tab>>();
+        let second_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                page_size: Some(2),
+                cursor: first_page.next_cursor.clone(),
+                sort: Some(TabSort::Url),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let desc_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                sort: Some(TabSort::Url),
+                direction: Some(SortDirection::Desc),
+                ..Default::default()
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        let urls = |page: PaginatedResult<Tab>| -> Vec<String> {
+            page.results.into_iter().map(|t| t.url).collect()
+        };
+        first_resp.assert_status_ok();
+        assert!(first_page.has_more);
+        assert_eq!(urls(first_page), vec!["https://a.com", "https://b.com"]);
+        second_resp.assert_status_ok();
+        let second_page = second_resp.json::<PaginatedResult<Tab>>();
+        assert!(!second_page.has_more);
+        assert_eq!(urls(second_page), vec!["https://c.com"]);
+        desc_resp.assert_status_ok();
+        assert_eq!(
+            urls(desc_resp.json::<PaginatedResult<Tab>>()),
+            vec!["https://c.com", "https://b.com", "https://a.com"]
+        );
+        assert_eq!(tabs.len(), 3);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_domain_and_dates() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = [
+            "https://github.com/swizzard/tmt-web",
+            "https://docs.github.com/en",
+            "https://notgithub.com/",
+            "https://example.com/github.com",
+            "http://[::1]:8080/github.com",
+        ]
+        .into_iter()
+        .map(|url| NewTab {
+            user_id: user_id.clone(),
+            url: url.into(),
+            notes: None,
+            title: None,
+        })
+        .collect();
+        let c = pool.get().await?;
+        let tabs = tabs::bulk_insert_tabs(c, tab_data).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let yesterday = (chrono::Utc::now() - chrono::Duration::days(1))
+            .date_naive()
+            .format("%Y-%m-%d")
+            .to_string();
+        let since_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                domain: Some("GitHub.com".into()),
+                created_after: Some(yesterday.clone()),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let before_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                domain: Some("github.com".into()),
+                created_before: Some(yesterday),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let ipv6_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                domain: Some("[::1]".into()),
+                ..Default::default()
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        since_resp.assert_status_ok();
+        let mut gotten_ids: Vec<String> = since_resp
+            .json::<PaginatedResult<Tab>>()
+            .results
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        gotten_ids.sort();
+        let mut expected_ids: Vec<String> = tabs[..2].iter().map(|t| t.id.clone()).collect();
+        expected_ids.sort();
+        assert_eq!(gotten_ids, expected_ids);
+        before_resp.assert_status_ok();
+        assert!(before_resp
+            .json::<PaginatedResult<Tab>>()
+            .results
+            .is_empty());
+        ipv6_resp.assert_status_ok();
+        let ipv6_ids: Vec<String> = ipv6_resp
+            .json::<PaginatedResult<Tab>>()
+            .results
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ipv6_ids, vec![tabs[4].id.clone()]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_bad_date_range() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                created_after: Some("2026-10-18T12:00:00Z".into()),
+                created_before: Some("2026-10-11".into()),
+                ..Default::default()
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_set_tab_status_ok() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let archive_resp = server
+            .put(&format!("/tabs/{}/status", &tabs[0].id))
+            .json(&TabStatusUpdate {
+                status: TabStatus::Archived,
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let read_resp = server
+            .put(&format!("/tabs/{}/status", &tabs[1].id))
+            .json(&TabStatusUpdate {
+                status: TabStatus::Read,
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let mut listings = Vec::new();
+        for status in [None, Some("archived"), Some("unread, read,archived")] {
+            let resp = server
+                .get(&format!("/users/{}/tabs", &user_id))
+                .add_query_params(TabListRequest {
+                    status: status.map(String::from),
+                    ..Default::default()
+                })
+                .add_header(header_name.clone(), header_value.clone())
+                .await;
+            resp.assert_status_ok();
+            listings.push(
+                resp.json::<PaginatedResult<Tab>>()
+                    .results
+                    .into_iter()
+                    .map(|t| t.id)
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        archive_resp.assert_status_ok();
+        let archived = archive_resp.json::<Tab>();
+        assert_eq!(archived.status, TabStatus::Archived);
+        assert_eq!(archived.modified_at, tabs[0].modified_at);
+        read_resp.assert_status_ok();
+        assert_eq!(read_resp.json::<Tab>().status, TabStatus::Read);
+        assert_eq!(listings[0], vec![tabs[1].id.clone(), tabs[2].id.clone()]);
+        assert_eq!(listings[1], vec![tabs[0].id.clone()]);
+        assert_eq!(
+            listings[2],
+            tabs.iter().map(|t| t.id.clone()).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_set_tab_status_doesnt_belong() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let mut other_user_data = Faker.fake::<NewConfirmedUser>();
+        other_user_data.confirmed = true;
+        let c = pool.get().await?;
+        let other_user = users::new_user_confirmed(c, other_user_data).await?;
+        let other_user_id = other_user.id.clone();
+        let c = pool.get().await?;
+        let other_tab = bulk_create_tabs(c, other_user_id.clone(), 1)
+            .await?
+            .remove(0);
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .put(&format!("/tabs/{}/status", &other_tab.id))
+            .json(&TabStatusUpdate {
+                status: TabStatus::Read,
+            })
+            .add_header(header_name, header_value)
+            .await;
+        let c = pool.get().await?;
+        let unchanged = tabs::get_tab(c, other_user_id.clone(), other_tab.id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, other_user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, other_user_id.clone()).await?;
+
+        resp.assert_status_not_found();
+        assert_eq!(unchanged.status, TabStatus::Unread);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_bad_status() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                status: Some("unread,deleted".into()),
+                ..Default::default()
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_by_tags_all() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        let c = pool.get().await?;
+        let tags = bulk_create_tags(c, user_id.clone(), 2).await?;
+        // tab 0 has both tags, tab 1 has only the first, tab 2 has none
+        let ntt = vec![
+            NewTabTag {
+                tab_id: tabs[0].id.clone(),
+                tag_id: tags[0].id.clone(),
+            },
+            NewTabTag {
+                tab_id: tabs[0].id.clone(),
+                tag_id: tags[1].id.clone(),
+            },
+            NewTabTag {
+                tab_id: tabs[1].id.clone(),
+                tag_id: tags[0].id.clone(),
+            },
+        ];
+        let c = pool.get().await?;
+        bulk_mk_tab_tags(c, ntt).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let list_info = TabListRequest {
+            tags: Some(format!("{},{}", tags[0].id, tags[1].id)),
+            mode: Some(TagMatchMode::All),
+            ..Default::default()
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(list_info)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status_ok();
         let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
         assert!(!paginated_tabs.has_more);
-        assert!(paginated_tabs.results.is_empty());
+        let mut gotten_ids: Vec<String> =
+            paginated_tabs.results.into_iter().map(|t| t.id).collect();
+        gotten_ids.sort();
+        let mut expected_ids: Vec<String> = vec![tabs[0].id.clone()];
+        expected_ids.sort();
+        assert_eq!(gotten_ids, expected_ids);
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_defaults() -> anyhow::Result<()> {
+    async fn test_get_user_tabs_by_tags_includes_descendants() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
         let mut user_data = Faker.fake::<NewConfirmedUser>();
@@ -563,38 +2204,156 @@ mod tests {
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_id = user.id.clone();
         let user_email = user.email.clone();
+
         let c = pool.get().await?;
-        let tabs = bulk_create_tabs(c, user_id.clone(), 50).await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+        let c = pool.get().await?;
+        let tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["lang".into(), "rust".into(), "async".into(), "go".into()],
+        )
+        .await?;
+        // lang > rust > async, go is top-level
+        for (child, parent) in [(1, 0), (2, 1)] {
+            let c = pool.get().await?;
+            tags::set_tag_parent(
+                c,
+                user_id.clone(),
+                tags[child].id.clone(),
+                Some(tags[parent].id.clone()),
+            )
+            .await?;
+        }
+        // tab 0 is tagged async, 1 go, 2 lang, 3 rust and go, 4 nothing
+        let ntt = [(0, 2), (1, 3), (2, 0), (3, 1), (3, 3)]
+            .into_iter()
+            .map(|(tab, tag)| NewTabTag {
+                tab_id: tabs[tab].id.clone(),
+                tag_id: tags[tag].id.clone(),
+            })
+            .collect();
+        let c = pool.get().await?;
+        bulk_mk_tab_tags(c, ntt).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
-            page: None,
-            page_size: None,
+        let any_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                tags: Some(tags[0].id.clone()),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let all_resp = server
+            .get(&format!("/users/{}/tabs", &user_id))
+            .add_query_params(TabListRequest {
+                tags: Some(format!("{},{}", tags[0].id, tags[3].id)),
+                mode: Some(TagMatchMode::All),
+                ..Default::default()
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        let ids = |resp: axum_test::TestResponse| -> Vec<String> {
+            resp.assert_status_ok();
+            let mut ids: Vec<String> = resp
+                .json::<PaginatedResult<Tab>>()
+                .results
+                .into_iter()
+                .map(|t| t.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        let mut expected = vec![tabs[0].id.clone(), tabs[2].id.clone(), tabs[3].id.clone()];
+        expected.sort();
+        assert_eq!(ids(any_resp), expected);
+        assert_eq!(ids(all_resp), vec![tabs[3].id.clone()]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_user_tabs_by_tags_any() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let c = pool.get().await?;
+        let tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        let c = pool.get().await?;
+        let tags = bulk_create_tags(c, user_id.clone(), 2).await?;
+        // tab 0 has both tags, tab 1 has only the first, tab 2 has none
+        let ntt = vec![
+            NewTabTag {
+                tab_id: tabs[0].id.clone(),
+                tag_id: tags[0].id.clone(),
+            },
+            NewTabTag {
+                tab_id: tabs[0].id.clone(),
+                tag_id: tags[1].id.clone(),
+            },
+            NewTabTag {
+                tab_id: tabs[1].id.clone(),
+                tag_id: tags[0].id.clone(),
+            },
+        ];
+        let c = pool.get().await?;
+        bulk_mk_tab_tags(c, ntt).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let list_info = TabListRequest {
+            tags: Some(format!("{},{}", tags[0].id, tags[1].id)),
+            mode: Some(TagMatchMode::Any),
+            ..Default::default()
         };
         let resp = server
             .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .add_query_params(list_info)
             .add_header(header_name, header_value)
             .await;
 
         let c = pool.get().await?;
-        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
         let c = pool.get().await?;
-        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
 
         resp.assert_status_ok();
         let paginated_tabs = resp.json::<PaginatedResult<Tab>>();
-        let expected_tabs = tabs.into_iter().take(25).collect::<Vec<_>>();
-        assert!(paginated_tabs.has_more);
-        assert_eq!(paginated_tabs.results, expected_tabs);
+        assert!(!paginated_tabs.has_more);
+        let mut gotten_ids: Vec<String> =
+            paginated_tabs.results.into_iter().map(|t| t.id).collect();
+        gotten_ids.sort();
+        let mut expected_ids: Vec<String> = vec![tabs[0].id.clone(), tabs[1].id.clone()];
+        expected_ids.sort();
+        assert_eq!(gotten_ids, expected_ids);
         Ok(())
     }
+
     #[test_log::test(tokio::test)]
-    async fn test_get_user_tabs_wrong_user() -> anyhow::Result<()> {
+    async fn test_search_user_tabs_ok() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tabs_router())?;
         let mut user_data = Faker.fake::<NewConfirmedUser>();
@@ -602,28 +2361,94 @@ mod tests {
         let c = pool.get().await?;
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = vec![
+            NewTab {
+                user_id: user_id.clone(),
+                url: String::from("https://example.com/learning"),
+                notes: Some(String::from("learning rust, slowly")),
+                title: None,
+            },
+            NewTab {
+                user_id: user_id.clone(),
+                url: String::from("https://example.com/cooking"),
+                notes: Some(String::from("soup")),
+                title: Some(String::from("Cooking")),
+            },
+            NewTab {
+                user_id: user_id.clone(),
+                url: String::from("https://doc.rust-lang.org/book"),
+                notes: None,
+                title: Some(String::from("The Rust Programming Language")),
+            },
+        ];
         let c = pool.get().await?;
-        let _tabs = bulk_create_tabs(c, user_id.clone(), 5).await?;
+        let tabs = tabs::bulk_insert_tabs(c, tab_data).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let search = TabSearchRequest {
+            q: String::from("rust"),
+            page: None,
+            page_size: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs/search", &user_id))
+            .add_query_params(search)
+            .add_header(header_name, header_value)
+            .await;
 
-        let mut login_user_data = Faker.fake::<NewConfirmedUser>();
-        login_user_data.confirmed = true;
         let c = pool.get().await?;
-        let login_user = users::new_user_confirmed(c, login_user_data).await?;
-        let login_user_email = login_user.email.clone();
+        let _ = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), login_user_email).await?;
+        resp.assert_status_ok();
+        let found = resp.json::<PaginatedResult<Tab>>();
+        assert!(!found.has_more);
+        // title matches rank above notes matches
+        let found_ids: Vec<String> = found.results.into_iter().map(|t| t.id).collect();
+        assert_eq!(found_ids, vec![tabs[2].id.clone(), tabs[0].id.clone()]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_search_user_tabs_pagination() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = (0..7)
+            .map(|i| NewTab {
+                user_id: user_id.clone(),
+                url: format!("https://example.com/{}", i),
+                notes: Some(String::from("needle")),
+                title: None,
+            })
+            .collect();
+        let c = pool.get().await?;
+        tabs::bulk_insert_tabs(c, tab_data).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
-        let pag_info = PaginationRequest {
+        let search = TabSearchRequest {
+            q: String::from("needle"),
             page: Some(1),
             page_size: Some(5),
         };
-
         let resp = server
-            .get(&format!("/users/{}/tabs", &user_id))
-            .add_query_params(pag_info)
+            .get(&format!("/users/{}/tabs/search", &user_id))
+            .add_query_params(search)
             .add_header(header_name, header_value)
             .await;
 
@@ -632,7 +2457,44 @@ mod tests {
         let c = pool.get().await?;
         let _ = users::deconfirm_user(c, user_id.clone()).await?;
 
-        resp.assert_status(StatusCode::FORBIDDEN);
+        resp.assert_status_ok();
+        let found = resp.json::<PaginatedResult<Tab>>();
+        assert!(found.has_more);
+        assert_eq!(found.results.len(), 5);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_search_user_tabs_empty_query() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let search = TabSearchRequest {
+            q: String::from("  "),
+            page: None,
+            page_size: None,
+        };
+        let resp = server
+            .get(&format!("/users/{}/tabs/search", &user_id))
+            .add_query_params(search)
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        let _ = users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::BAD_REQUEST);
         Ok(())
     }
 
@@ -667,7 +2529,7 @@ mod tests {
         let c = pool.get().await?;
         bulk_mk_tab_tags(c, ntt).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -686,10 +2548,10 @@ mod tests {
         resp.assert_status_ok();
         let tab_with_tags = resp.json::<TabWithTags>();
         assert_eq!(tab_with_tags.tab, tab);
-        assert_eq!(
-            tab_with_tags.tags,
-            to_attach_tags.cloned().collect::<Vec<_>>()
-        );
+        // tags come back alphabetically
+        let mut expected_tags = to_attach_tags.cloned().collect::<Vec<_>>();
+        expected_tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+        assert_eq!(tab_with_tags.tags, expected_tags);
 
         Ok(())
     }
@@ -707,7 +2569,7 @@ mod tests {
         let c = pool.get().await?;
         let tags = bulk_create_tags(c, user_id.clone(), 5).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -740,6 +2602,7 @@ mod tests {
                 user_id: user_id.clone(),
                 url: url.clone(),
                 notes: notes.clone(),
+                title: None,
             },
             tags: tags_data,
         };
@@ -763,4 +2626,146 @@ mod tests {
         assert_eq!(tab_with_tags.tags.len(), 5);
         Ok(())
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_with_tags_reads_tags_from_db() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let existing = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["by-id".into(), "by-name".into()],
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let tab_data = NewTabWithTags {
+            tab: NewTab {
+                user_id: user_id.clone(),
+                url: "https://example.com/tagged".into(),
+                notes: None,
+                title: None,
+            },
+            tags: vec![
+                // the client's text for an existing tag is ignored
+                MaybeNewTag {
+                    id: Some(existing[0].id.clone()),
+                    user_id: user_id.clone(),
+                    tag: "renamed".into(),
+                },
+                // an existing name is reused rather than failing on the unique index
+                MaybeNewTag {
+                    id: None,
+                    user_id: user_id.clone(),
+                    tag: "by-name".into(),
+                },
+                // new tags belong to the session user whatever the client says
+                MaybeNewTag {
+                    id: None,
+                    user_id: "someone else".into(),
+                    tag: "new".into(),
+                },
+            ],
+        };
+        let resp = server
+            .post("/tabs/with-tags")
+            .json(&tab_data)
+            .add_header(header_name, header_value)
+            .await;
+        let c = pool.get().await?;
+        let all_tags = tags::get_all_user_tags(c, user_id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::CREATED);
+        let tab_with_tags = resp.json::<TabWithTags>();
+        let names: Vec<&str> = tab_with_tags.tags.iter().map(|t| t.tag.as_str()).collect();
+        assert_eq!(names, vec!["by-id", "by-name", "new"]);
+        assert_eq!(tab_with_tags.tags[0].id, existing[0].id);
+        assert_eq!(tab_with_tags.tags[1].id, existing[1].id);
+        assert!(tab_with_tags.tags.iter().all(|t| t.user_id == user_id));
+        assert_eq!(all_tags.len(), 3);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_with_tags_foreign_tag() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tabs_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let mut other_data = Faker.fake::<NewConfirmedUser>();
+        other_data.confirmed = true;
+        let c = pool.get().await?;
+        let other = users::new_user_confirmed(c, other_data).await?;
+        let c = pool.get().await?;
+        let other_tag = bulk_create_tags(c, other.id.clone(), 1).await?.remove(0);
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let tab_data = NewTabWithTags {
+            tab: NewTab {
+                user_id: user_id.clone(),
+                url: "https://example.com/foreign".into(),
+                notes: None,
+                title: None,
+            },
+            tags: vec![
+                MaybeNewTag {
+                    id: None,
+                    user_id: user_id.clone(),
+                    tag: "mine".into(),
+                },
+                MaybeNewTag {
+                    id: Some(other_tag.id.clone()),
+                    user_id: user_id.clone(),
+                    tag: other_tag.tag.clone(),
+                },
+            ],
+        };
+        let resp = server
+            .post("/tabs/with-tags")
+            .json(&tab_data)
+            .add_header(header_name, header_value)
+            .await;
+        let c = pool.get().await?;
+        let created_tabs = tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let created_tags = tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, other.id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, other.id.clone()).await?;
+
+        resp.assert_status(StatusCode::FORBIDDEN);
+        // nothing is left behind
+        assert_eq!(created_tabs, 0);
+        assert_eq!(created_tags, 0);
+        Ok(())
+    }
 }
@@ -3,15 +3,18 @@ use crate::{
     models::{
         session::Session,
         tab::{AttachTagRequest, DetachTagRequest, TagDetachedResponse},
-        tag::{MatchedTags, NewTag, Tag},
+        tag::{
+            MatchedTags, MergeTagsRequest, NewTag, Tag, TagChangeset, TagNode, TagParentUpdate,
+            TagUpdate, TagWithCount, TagsDeletedResponse,
+        },
     },
-    types::{AppError, AppState, MatchFragmentRequest, PaginatedResult, PaginationRequest},
+    types::{AppError, AppState, MatchFragmentRequest, PaginatedResult, TagListRequest},
 };
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 
@@ -20,9 +23,16 @@ pub fn tags_router() -> Router<AppState> {
         .route("/tabs/:tab_id/tags", post(attach))
         .route("/tabs/:tab_id/tags/:tag_id", delete(detach))
         .route("/tags", post(create))
-        .route("/tags/:tag_id", delete(delete_tag))
+        .route("/tags/:tag_id", delete(delete_tag).patch(update_tag))
+        .route("/tags/:tag_id/merge", post(merge_tag))
+        .route("/tags/:tag_id/parent", put(set_tag_parent))
         .route("/users/:user_id/tags", get(user_tags))
         .route("/users/:user_id/tags/fuzzy", get(user_tags_fuzzy))
+        .route("/users/:user_id/tags/tree", get(user_tag_tree))
+        .route(
+            "/users/:user_id/tags/unused",
+            get(unused_tags).delete(delete_unused_tags),
+        )
 }
 
 async fn create(
@@ -84,37 +94,135 @@ async fn delete_tag(
     Ok(StatusCode::OK)
 }
 
+async fn update_tag(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tag_id): Path<String>,
+    Json(payload): Json<TagUpdate>,
+) -> Result<Json<Tag>, AppError> {
+    if payload.is_empty() {
+        return Err(AppError::BadRequest);
+    }
+    let changes = TagChangeset::try_from(payload)?;
+    let conn = st.conn().await?;
+    Ok(Json(
+        tags::update_tag(conn, session.user_id, tag_id, changes).await?,
+    ))
+}
+
+async fn merge_tag(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tag_id): Path<String>,
+    Json(MergeTagsRequest { source_tag_id }): Json<MergeTagsRequest>,
+) -> Result<Json<Tag>, AppError> {
+    if tag_id == source_tag_id {
+        return Err(AppError::BadRequest);
+    }
+    let conn = st.conn().await?;
+    Ok(Json(
+        tags::merge_tags(conn, session.user_id, tag_id, source_tag_id).await?,
+    ))
+}
+
+async fn set_tag_parent(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tag_id): Path<String>,
+    Json(TagParentUpdate { parent_id }): Json<TagParentUpdate>,
+) -> Result<Json<Tag>, AppError> {
+    let conn = st.conn().await?;
+    Ok(Json(
+        tags::set_tag_parent(conn, session.user_id, tag_id, parent_id).await?,
+    ))
+}
+
 async fn user_tags(
     State(st): State<AppState>,
     session: Session,
     Path(user_id): Path<String>,
-    Query(pr): Query<PaginationRequest>,
-) -> Result<Json<PaginatedResult<Tag>>, AppError> {
+    Query(lr): Query<TagListRequest>,
+) -> Result<Json<PaginatedResult<TagWithCount>>, AppError> {
     if user_id != session.user_id {
         return Err(AppError::WrongCredentials);
     }
     let conn = st.conn().await?;
-    Ok(Json(tags::get_user_tags(conn, session.user_id, pr).await?))
+    Ok(Json(
+        tags::get_user_tags(
+            conn,
+            session.user_id,
+            lr.pagination(),
+            lr.sort.unwrap_or_default(),
+        )
+        .await?,
+    ))
 }
 
+const MAX_FRAGMENT_LEN: usize = 20;
+const DEFAULT_MATCH_LIMIT: i64 = 10;
+const MAX_MATCH_LIMIT: i64 = 50;
+
 async fn user_tags_fuzzy(
     State(st): State<AppState>,
     session: Session,
     Path(user_id): Path<String>,
-    Query(MatchFragmentRequest { fragment }): Query<MatchFragmentRequest>,
+    Query(MatchFragmentRequest { fragment, limit }): Query<MatchFragmentRequest>,
 ) -> Result<Json<MatchedTags>, AppError> {
     if user_id != session.user_id {
         return Err(AppError::WrongCredentials);
     }
-    if fragment.len() < 3 || fragment.len() > 20 {
+    // in characters, so non-ASCII tags get the same room
+    if fragment.trim().is_empty() || fragment.chars().count() > MAX_FRAGMENT_LEN {
+        return Err(AppError::BadRequest);
+    }
+    let limit = limit.unwrap_or(DEFAULT_MATCH_LIMIT);
+    if !(1..=MAX_MATCH_LIMIT).contains(&limit) {
         return Err(AppError::BadRequest);
     }
     let conn = st.conn().await?;
     Ok(Json(MatchedTags::new(
-        tags::get_user_tags_fuzzy(conn, session.user_id, fragment).await?,
+        tags::get_user_tags_fuzzy(conn, session.user_id, fragment, limit).await?,
     )))
 }
 
+async fn user_tag_tree(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<Vec<TagNode>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let tags = tags::get_all_user_tags(conn, session.user_id).await?;
+    Ok(Json(TagNode::tree(tags)))
+}
+
+async fn unused_tags(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<Vec<Tag>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    Ok(Json(tags::get_unused_tags(conn, session.user_id).await?))
+}
+
+async fn delete_unused_tags(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<TagsDeletedResponse>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let deleted = tags::delete_unused_tags(conn, session.user_id).await?;
+    Ok(Json(TagsDeletedResponse { deleted }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,13 +230,18 @@ mod tests {
         db::{
             sessions, tabs,
             test_util::{
-                bulk_create_tags, bulk_create_tags_from_strings, create_tags_reverse_alpha,
+                bulk_create_tabs, bulk_create_tags, bulk_create_tags_from_strings,
+                create_tags_reverse_alpha,
             },
             users,
         },
-        models::{tab::NewTab, user::NewConfirmedUser},
+        metadata::FakeMetadataFetcher,
+        models::{
+            tab::{NewTab, NewTabTag},
+            user::NewConfirmedUser,
+        },
         routes::_test_utils::test_app,
-        types::{test_pool_from_env, Claims},
+        types::{test_pool_from_env, Claims, PaginationRequest, TagSort},
     };
     use fake::{Fake, Faker};
     use http::header;
@@ -143,7 +256,7 @@ mod tests {
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_email = user.email.clone();
         let user_id = user.id.clone();
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -179,7 +292,7 @@ mod tests {
         let user = users::new_user_confirmed(c, user_data).await?;
         let user_email = user.email.clone();
         let user_id = user.id.clone();
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -216,14 +329,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -272,14 +384,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -325,14 +436,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -386,14 +496,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&other_user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -447,14 +556,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&other_user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -500,8 +608,7 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let tab_id = tab.id.clone();
 
         let mut tag_data = Faker.fake::<NewTag>();
@@ -513,7 +620,7 @@ mod tests {
         let c = pool.get().await?;
         tags::mk_tab_tag(c, tab_id.clone(), tag_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -554,8 +661,7 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let tab_id = tab.id.clone();
 
         let mut tag_data = Faker.fake::<NewTag>();
@@ -564,7 +670,7 @@ mod tests {
         let tag = tags::new_tag(c, tag_data).await?;
         let tag_id = tag.id.clone();
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -603,14 +709,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&other_user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -654,14 +759,13 @@ mod tests {
 
         let mut tab_data = Faker.fake::<NewTab>();
         tab_data.user_id.clone_from(&user_id);
-        let c = pool.get().await?;
-        let tab = tabs::new_tab(c, tab_data).await?;
+        let tab = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data).await?;
         let mut tag_data = Faker.fake::<NewTag>();
         tag_data.user_id.clone_from(&other_user_id);
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -702,7 +806,7 @@ mod tests {
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -735,7 +839,7 @@ mod tests {
         let user_id = user.id.clone();
         let tag_id = Faker.fake::<String>();
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -779,7 +883,7 @@ mod tests {
         let c = pool.get().await?;
         let tag = tags::new_tag(c, tag_data).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -819,7 +923,7 @@ mod tests {
         let c = pool.get().await?;
         bulk_create_tags(c, user_id.clone(), 5).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -827,6 +931,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(1),
             page_size: Some(5),
+            cursor: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags", &user_id))
@@ -859,7 +964,7 @@ mod tests {
         let c = pool.get().await?;
         let tags = create_tags_reverse_alpha(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -867,6 +972,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(1),
             page_size: Some(5),
+            cursor: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags", &user_id))
@@ -901,7 +1007,7 @@ mod tests {
         let c = pool.get().await?;
         let tags = create_tags_reverse_alpha(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -909,6 +1015,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(6),
             page_size: Some(5),
+            cursor: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags", &user_id))
@@ -943,7 +1050,7 @@ mod tests {
         let c = pool.get().await?;
         create_tags_reverse_alpha(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -951,6 +1058,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(7),
             page_size: Some(5),
+            cursor: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags", &user_id))
@@ -971,6 +1079,127 @@ mod tests {
         Ok(())
     }
     #[test_log::test(tokio::test)]
+    async fn test_user_tags_cursor() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let mut tags = bulk_create_tags(c, user_id.clone(), 5).await?;
+        // created together, so they're ordered by id
+        tags.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let first_resp = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_query_params(PaginationRequest {
+                page: None,
+                page_size: Some(3),
+                cursor: Some(String::new()),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let first_page = first_resp.json::<PaginatedResult<Tag>>();
+        let second_resp = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_query_params(PaginationRequest {
+                page: None,
+                page_size: Some(3),
+                cursor: first_page.next_cursor.clone(),
+            })
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        first_resp.assert_status_ok();
+        assert!(first_page.has_more);
+        assert_eq!(first_page.results, tags[..3]);
+        second_resp.assert_status_ok();
+        let second_page = second_resp.json::<PaginatedResult<Tag>>();
+        assert!(!second_page.has_more);
+        assert!(second_page.next_cursor.is_none());
+        assert_eq!(second_page.results, tags[3..]);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
+    async fn test_user_tags_cursor_pinned() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let mut tags = bulk_create_tags(c, user_id.clone(), 5).await?;
+        tags.sort_by(|a, b| a.id.cmp(&b.id));
+        for i in [3, 1] {
+            let c = pool.get().await?;
+            tags[i] = tags::update_tag(
+                c,
+                user_id.clone(),
+                tags[i].id.clone(),
+                TagChangeset {
+                    tag: None,
+                    color: None,
+                    description: None,
+                    pinned: Some(true),
+                },
+            )
+            .await?;
+        }
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let mut pages = Vec::new();
+        let mut cursor = Some(String::new());
+        while let Some(c) = cursor {
+            let resp = server
+                .get(&format!("/users/{}/tags", &user_id))
+                .add_query_params(PaginationRequest {
+                    page: None,
+                    page_size: Some(2),
+                    cursor: Some(c),
+                })
+                .add_header(header_name.clone(), header_value.clone())
+                .await;
+            resp.assert_status_ok();
+            let page = resp.json::<PaginatedResult<Tag>>();
+            cursor = page.next_cursor.clone();
+            pages.push(page.results);
+        }
+
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        // pinned first, each part in creation order
+        let expected = vec![
+            vec![tags[1].clone(), tags[3].clone()],
+            vec![tags[0].clone(), tags[2].clone()],
+            vec![tags[4].clone()],
+        ];
+        assert_eq!(pages, expected);
+        Ok(())
+    }
+    #[test_log::test(tokio::test)]
     async fn test_user_tags_no_tags() -> anyhow::Result<()> {
         let pool = test_pool_from_env();
         let server = test_app(tags_router())?;
@@ -981,7 +1210,7 @@ mod tests {
         let user_id = user.id.clone();
         let user_email = user.email.clone();
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -989,6 +1218,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(1),
             page_size: Some(5),
+            cursor: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags", &user_id))
@@ -1019,7 +1249,7 @@ mod tests {
         let c = pool.get().await?;
         create_tags_reverse_alpha(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -1027,6 +1257,7 @@ mod tests {
         let pag_info = PaginationRequest {
             page: Some(1),
             page_size: Some(5),
+            cursor: None,
         };
         let other_user_id = Faker.fake::<String>();
         let resp = server
@@ -1065,13 +1296,14 @@ mod tests {
         ];
         bulk_create_tags_from_strings(c, user_id.clone(), ts).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
         let frag = MatchFragmentRequest {
             fragment: "dog".to_string(),
+            limit: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags/fuzzy", &user_id))
@@ -1085,8 +1317,13 @@ mod tests {
         tags::delete_user_tags(c, user_id.clone()).await?;
 
         resp.assert_status_ok();
-        let gotten_tags = resp.json::<MatchedTags>();
-        assert_eq!(gotten_tags.matches.len(), 3);
+        let gotten_tags: Vec<String> = resp
+            .json::<MatchedTags>()
+            .matches
+            .into_iter()
+            .map(|t| t.tag)
+            .collect();
+        assert_eq!(gotten_tags, vec!["dog", "doggy", "a dog"]);
 
         Ok(())
     }
@@ -1103,7 +1340,7 @@ mod tests {
         let c = pool.get().await?;
         create_tags_reverse_alpha(c, user_id.clone()).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
@@ -1111,6 +1348,7 @@ mod tests {
         let other_user_id = Faker.fake::<String>();
         let frag = MatchFragmentRequest {
             fragment: "a".to_string(),
+            limit: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags/fuzzy", &other_user_id))
@@ -1147,13 +1385,14 @@ mod tests {
         ];
         bulk_create_tags_from_strings(c, user_id.clone(), ts).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
         let frag = MatchFragmentRequest {
-            fragment: "d".to_string(),
+            fragment: " ".to_string(),
+            limit: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags/fuzzy", &user_id))
@@ -1191,13 +1430,14 @@ mod tests {
         ];
         bulk_create_tags_from_strings(c, user_id.clone(), ts).await?;
 
-        let session = sessions::new_session(pool.clone(), user_email).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
         let token = Claims::from_session(&session).test_to_token()?;
         let bearer = format!("Bearer {}", token);
         let header_value = header::HeaderValue::from_str(&bearer)?;
         let header_name = header::AUTHORIZATION;
         let frag = MatchFragmentRequest {
             fragment: "doyoubelievehowlongthisisthough".to_string(),
+            limit: None,
         };
         let resp = server
             .get(&format!("/users/{}/tags/fuzzy", &user_id))
@@ -1214,4 +1454,654 @@ mod tests {
 
         Ok(())
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rename_tag() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags =
+            bulk_create_tags_from_strings(c, user_id.clone(), vec!["rust".into(), "go".into()])
+                .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let renamed = server
+            .patch(&format!("/tags/{}", &user_tags[0].id))
+            .json(&TagUpdate {
+                tag: Some("rust-lang".into()),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let collision = server
+            .patch(&format!("/tags/{}", &user_tags[0].id))
+            .json(&TagUpdate {
+                tag: Some("go".into()),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let missing = server
+            .patch(&format!("/tags/{}", Faker.fake::<String>()))
+            .json(&TagUpdate {
+                tag: Some("c".into()),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        renamed.assert_status_ok();
+        let tag = renamed.json::<Tag>();
+        assert_eq!(tag.id, user_tags[0].id);
+        assert_eq!(tag.tag, "rust-lang");
+        collision.assert_status(StatusCode::CONFLICT);
+        assert_eq!(collision.json::<serde_json::Value>()["id"], user_tags[1].id);
+        missing.assert_status(StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_update_tag_metadata() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags =
+            bulk_create_tags_from_strings(c, user_id.clone(), vec!["a".into(), "z".into()]).await?;
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 1).await?;
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(
+            c,
+            vec![NewTabTag {
+                tab_id: user_tabs[0].id.clone(),
+                tag_id: user_tags[0].id.clone(),
+            }],
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let updated = server
+            .patch(&format!("/tags/{}", &user_tags[0].id))
+            .json(&TagUpdate {
+                color: Some(Some("#3366CC".into())),
+                description: Some(Some("the first letter".into())),
+                pinned: Some(true),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let bad_color = server
+            .patch(&format!("/tags/{}", &user_tags[0].id))
+            .json(&TagUpdate {
+                color: Some(Some("blue".into())),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let empty = server
+            .patch(&format!("/tags/{}", &user_tags[0].id))
+            .json(&TagUpdate::default())
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let listed = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let tab =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), user_tabs[0].id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        updated.assert_status_ok();
+        let tag = updated.json::<Tag>();
+        assert_eq!(tag.tag, "a");
+        assert_eq!(tag.color.as_deref(), Some("#3366cc"));
+        assert_eq!(tag.description.as_deref(), Some("the first letter"));
+        assert!(tag.pinned);
+        bad_color.assert_status(StatusCode::BAD_REQUEST);
+        empty.assert_status(StatusCode::BAD_REQUEST);
+        listed.assert_status_ok();
+        // pinned first, though the rest are listed in reverse
+        let names: Vec<String> = listed
+            .json::<PaginatedResult<TagWithCount>>()
+            .results
+            .into_iter()
+            .map(|t| t.tag.tag)
+            .collect();
+        assert_eq!(names, vec!["a", "z"]);
+        assert_eq!(tab.tags, vec![tag]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_merge_tags() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["rust".into(), "Rust-lang".into()],
+        )
+        .await?;
+        let (target, source) = (&user_tags[0], &user_tags[1]);
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        // tab 0 has both tags, tab 1 only the source and tab 2 only the target
+        let ntt = [(0, source), (0, target), (1, source), (2, target)]
+            .into_iter()
+            .map(|(i, tag)| NewTabTag {
+                tab_id: user_tabs[i].id.clone(),
+                tag_id: tag.id.clone(),
+            })
+            .collect();
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(c, ntt).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let same = server
+            .post(&format!("/tags/{}/merge", &target.id))
+            .json(&MergeTagsRequest {
+                source_tag_id: target.id.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let merged = server
+            .post(&format!("/tags/{}/merge", &target.id))
+            .json(&MergeTagsRequest {
+                source_tag_id: source.id.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let again = server
+            .post(&format!("/tags/{}/merge", &target.id))
+            .json(&MergeTagsRequest {
+                source_tag_id: source.id.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let mut tab_tags = Vec::with_capacity(user_tabs.len());
+        for tab in user_tabs.iter() {
+            let tab =
+                tabs::get_tab_with_tags(pool.clone(), user_id.clone(), tab.id.clone()).await?;
+            tab_tags.push(tab.tags.into_iter().map(|t| t.id).collect::<Vec<String>>());
+        }
+        let c = pool.get().await?;
+        let remaining = tags::get_all_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let trashed = tags::get_trashed_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let restored = tags::restore_tag(c, user_id.clone(), source.id.clone()).await?;
+        let restored_tab =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), user_tabs[1].id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        same.assert_status(StatusCode::BAD_REQUEST);
+        merged.assert_status_ok();
+        assert_eq!(merged.json::<Tag>(), target.clone());
+        again.assert_status(StatusCode::NOT_FOUND);
+        let only_target = vec![target.id.clone()];
+        assert_eq!(
+            tab_tags,
+            vec![only_target.clone(), only_target.clone(), only_target]
+        );
+        assert_eq!(remaining, vec![target.clone()]);
+        // the source goes to the trash, and comes back still on its tabs
+        assert_eq!(
+            trashed.into_iter().map(|t| t.id).collect::<Vec<String>>(),
+            vec![source.id.clone()]
+        );
+        assert_eq!(restored.id, source.id);
+        let mut restored_tags: Vec<String> = restored_tab.tags.into_iter().map(|t| t.id).collect();
+        restored_tags.sort();
+        let mut expected_tags = vec![source.id.clone(), target.id.clone()];
+        expected_tags.sort();
+        assert_eq!(restored_tags, expected_tags);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_tag_parents_and_tree() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["lang".into(), "rust".into(), "async".into(), "go".into()],
+        )
+        .await?;
+        let (lang, rust, asyncs, go) = (&user_tags[0], &user_tags[1], &user_tags[2], &user_tags[3]);
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let set_parent = |tag: &Tag, parent: Option<&Tag>| {
+            server
+                .put(&format!("/tags/{}/parent", &tag.id))
+                .json(&TagParentUpdate {
+                    parent_id: parent.map(|p| p.id.clone()),
+                })
+                .add_header(header_name.clone(), header_value.clone())
+        };
+
+        let rust_resp = set_parent(rust, Some(lang)).await;
+        let async_resp = set_parent(asyncs, Some(rust)).await;
+        let go_resp = set_parent(go, Some(lang)).await;
+        let cycle = set_parent(lang, Some(asyncs)).await;
+        let own_parent = set_parent(lang, Some(lang)).await;
+        let cleared = set_parent(go, None).await;
+        let missing_parent = server
+            .put(&format!("/tags/{}/parent", &go.id))
+            .json(&TagParentUpdate {
+                parent_id: Some(Faker.fake::<String>()),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let tree = server
+            .get(&format!("/users/{}/tags/tree", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        rust_resp.assert_status_ok();
+        assert_eq!(rust_resp.json::<Tag>().parent_id, Some(lang.id.clone()));
+        async_resp.assert_status_ok();
+        go_resp.assert_status_ok();
+        cycle.assert_status(StatusCode::BAD_REQUEST);
+        own_parent.assert_status(StatusCode::BAD_REQUEST);
+        cleared.assert_status_ok();
+        assert_eq!(cleared.json::<Tag>().parent_id, None);
+        missing_parent.assert_status(StatusCode::NOT_FOUND);
+        tree.assert_status_ok();
+        let tree = tree.json::<Vec<TagNode>>();
+        let names = |nodes: &[TagNode]| -> Vec<String> {
+            nodes.iter().map(|n| n.tag.tag.clone()).collect()
+        };
+        assert_eq!(names(&tree), vec!["go", "lang"]);
+        assert_eq!(names(&tree[1].children), vec!["rust"]);
+        assert_eq!(names(&tree[1].children[0].children), vec!["async"]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_merge_tag_into_descendant() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["top".into(), "mid".into(), "leaf".into(), "sibling".into()],
+        )
+        .await?;
+        // top > mid > leaf and mid > sibling; leaf is merged into from mid
+        for (child, parent) in [(1, 0), (2, 1), (3, 1)] {
+            let c = pool.get().await?;
+            tags::set_tag_parent(
+                c,
+                user_id.clone(),
+                user_tags[child].id.clone(),
+                Some(user_tags[parent].id.clone()),
+            )
+            .await?;
+        }
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let merged = server
+            .post(&format!("/tags/{}/merge", &user_tags[2].id))
+            .json(&MergeTagsRequest {
+                source_tag_id: user_tags[1].id.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let tree = TagNode::tree(tags::get_all_user_tags(c, user_id.clone()).await?);
+
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        merged.assert_status_ok();
+        assert_eq!(
+            merged.json::<Tag>().parent_id,
+            Some(user_tags[0].id.clone())
+        );
+        // leaf takes mid's place, and mid's other children
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].tag.tag, "top");
+        assert_eq!(tree[0].children.len(), 1);
+        assert_eq!(tree[0].children[0].tag.tag, "leaf");
+        assert_eq!(tree[0].children[0].children.len(), 1);
+        assert_eq!(tree[0].children[0].children[0].tag.tag, "sibling");
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_user_tags_usage() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec!["a".into(), "b".into(), "c".into()],
+        )
+        .await?;
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        // "c" is on every tab, "a" on one and "b" on none; tab 2 goes in the trash
+        let ntt = [(0, 2), (1, 2), (2, 2), (0, 0), (2, 1)]
+            .into_iter()
+            .map(|(tab, tag)| NewTabTag {
+                tab_id: user_tabs[tab].id.clone(),
+                tag_id: user_tags[tag].id.clone(),
+            })
+            .collect();
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(c, ntt).await?;
+        let c = pool.get().await?;
+        tabs::delete_tab(c, user_id.clone(), user_tabs[2].id.clone()).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let by_usage = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_query_params(TagListRequest {
+                sort: Some(TagSort::Usage),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let by_tag = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let first = by_tag.json::<PaginatedResult<TagWithCount>>();
+        let cursor = server
+            .get(&format!("/users/{}/tags", &user_id))
+            .add_query_params(TagListRequest {
+                cursor: Some(String::new()),
+                sort: Some(TagSort::Usage),
+                ..Default::default()
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        by_usage.assert_status_ok();
+        let counts: Vec<(String, i64)> = by_usage
+            .json::<PaginatedResult<TagWithCount>>()
+            .results
+            .into_iter()
+            .map(|t| (t.tag.tag, t.tab_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("c".into(), 2), ("a".into(), 1), ("b".into(), 0)]
+        );
+        by_tag.assert_status_ok();
+        let names: Vec<String> = first.results.into_iter().map(|t| t.tag.tag).collect();
+        assert_eq!(names, vec!["c", "b", "a"]);
+        cursor.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_unused_tags() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tags = bulk_create_tags_from_strings(
+            c,
+            user_id.clone(),
+            vec![
+                "used".into(),
+                "parent".into(),
+                "child".into(),
+                "stale".into(),
+            ],
+        )
+        .await?;
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 2).await?;
+        // "stale" is only on a tab in the trash, "parent" only has a child that's in use
+        let ntt = [(0, 0), (0, 2), (1, 3)]
+            .into_iter()
+            .map(|(tab, tag)| NewTabTag {
+                tab_id: user_tabs[tab].id.clone(),
+                tag_id: user_tags[tag].id.clone(),
+            })
+            .collect();
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(c, ntt).await?;
+        let c = pool.get().await?;
+        tags::set_tag_parent(
+            c,
+            user_id.clone(),
+            user_tags[2].id.clone(),
+            Some(user_tags[1].id.clone()),
+        )
+        .await?;
+        let c = pool.get().await?;
+        tabs::delete_tab(c, user_id.clone(), user_tabs[1].id.clone()).await?;
+        let c = pool.get().await?;
+        let empty = tags::new_tag(
+            c,
+            NewTag {
+                user_id: user_id.clone(),
+                tag: "empty".into(),
+            },
+        )
+        .await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let wrong_user = server
+            .delete(&format!("/users/{}/tags/unused", "not-my-id"))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let unused = server
+            .get(&format!("/users/{}/tags/unused", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let deleted = server
+            .delete(&format!("/users/{}/tags/unused", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let remaining = tags::get_all_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let trashed = tags::get_trashed_tags(c, user_id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        wrong_user.assert_status(StatusCode::FORBIDDEN);
+        unused.assert_status_ok();
+        let unused: Vec<String> = unused
+            .json::<Vec<Tag>>()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(unused, vec![empty.id.clone(), user_tags[3].id.clone()]);
+        deleted.assert_status_ok();
+        assert_eq!(deleted.json::<TagsDeletedResponse>().deleted, 2);
+        let remaining: Vec<&str> = remaining.iter().map(|t| t.tag.as_str()).collect();
+        assert_eq!(remaining, vec!["child", "parent", "used"]);
+        let mut trashed: Vec<String> = trashed.into_iter().map(|t| t.tag).collect();
+        trashed.sort();
+        assert_eq!(trashed, vec!["empty", "stale"]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_user_tags_fuzzy_ranking() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(tags_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let ts = vec![
+            "javascript".into(),
+            "java".into(),
+            "rust".into(),
+            "ünïcödé".into(),
+        ];
+        bulk_create_tags_from_strings(c, user_id.clone(), ts).await?;
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+
+        let mut resps = Vec::new();
+        for (fragment, limit) in [
+            ("j", None),
+            ("j", Some(1)),
+            ("javscript", None),
+            ("ünï", None),
+            ("ü", Some(0)),
+        ] {
+            let resp = server
+                .get(&format!("/users/{}/tags/fuzzy", &user_id))
+                .add_query_params(MatchFragmentRequest {
+                    fragment: fragment.to_string(),
+                    limit,
+                })
+                .add_header(header_name.clone(), header_value.clone())
+                .await;
+            resps.push(resp);
+        }
+        // 20 characters but 40 bytes
+        let long = server
+            .get(&format!("/users/{}/tags/fuzzy", &user_id))
+            .add_query_params(MatchFragmentRequest {
+                fragment: "ü".repeat(20),
+                limit: None,
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+
+        let matched = |resp: &axum_test::TestResponse| -> Vec<String> {
+            resp.assert_status_ok();
+            resp.json::<MatchedTags>()
+                .matches
+                .into_iter()
+                .map(|t| t.tag)
+                .collect()
+        };
+        assert_eq!(matched(&resps[0]), vec!["java", "javascript"]);
+        assert_eq!(matched(&resps[1]), vec!["java"]);
+        assert_eq!(matched(&resps[2]), vec!["javascript"]);
+        assert_eq!(matched(&resps[3]), vec!["ünïcödé"]);
+        resps[4].assert_status(StatusCode::BAD_REQUEST);
+        assert!(matched(&long).is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,445 @@
+use crate::{
+    db::{tabs, tags},
+    models::{
+        session::Session,
+        tab::{Tab, Trash},
+        tag::Tag,
+    },
+    types::{AppError, AppState},
+};
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+
+pub fn trash_router() -> Router<AppState> {
+    Router::new()
+        .route("/users/:user_id/trash", get(user_trash))
+        .route("/tabs/:tab_id/restore", post(restore_tab))
+        .route("/tags/:tag_id/restore", post(restore_tag))
+}
+
+async fn user_trash(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<Trash>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let tabs = tabs::get_trashed_tabs(conn, session.user_id.clone()).await?;
+    let conn = st.conn().await?;
+    let tags = tags::get_trashed_tags(conn, session.user_id).await?;
+    Ok(Json(Trash { tabs, tags }))
+}
+
+async fn restore_tab(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tab_id): Path<String>,
+) -> Result<Json<Tab>, AppError> {
+    let conn = st.conn().await?;
+    let tab = tabs::restore_tab(conn, session.user_id, tab_id).await?;
+    Ok(Json(tab))
+}
+
+async fn restore_tag(
+    State(st): State<AppState>,
+    session: Session,
+    Path(tag_id): Path<String>,
+) -> Result<Json<Tag>, AppError> {
+    let conn = st.conn().await?;
+    let tag = tags::restore_tag(conn, session.user_id, tag_id).await?;
+    Ok(Json(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{
+            sessions,
+            test_util::{bulk_create_tabs, bulk_create_tags_from_strings},
+            users,
+        },
+        metadata::FakeMetadataFetcher,
+        models::{
+            tab::{NewTab, NewTabTag},
+            user::NewConfirmedUser,
+        },
+        routes::_test_utils::test_app,
+        schema::{tabs::dsl as tabs_dsl, tags::dsl as tags_dsl},
+        types::{test_pool_from_env, Claims, PaginationRequest, TabFilter},
+    };
+    use diesel::prelude::*;
+    use fake::{Fake, Faker};
+    use http::{header, StatusCode};
+
+    #[test_log::test(tokio::test)]
+    async fn test_trash_and_restore_tab() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(trash_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 2).await?;
+        let c = pool.get().await?;
+        let tag = bulk_create_tags_from_strings(c, user_id.clone(), vec!["kept".into()])
+            .await?
+            .remove(0);
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(
+            c,
+            vec![NewTabTag {
+                tab_id: user_tabs[0].id.clone(),
+                tag_id: tag.id.clone(),
+            }],
+        )
+        .await?;
+        let c = pool.get().await?;
+        tabs::delete_tab(c, user_id.clone(), user_tabs[0].id.clone()).await?;
+        let listed_while_trashed = tabs::get_user_tabs(
+            pool.clone(),
+            user_id.clone(),
+            TabFilter::default(),
+            PaginationRequest {
+                page: None,
+                page_size: None,
+                cursor: None,
+            },
+        )
+        .await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let trash_resp = server
+            .get(&format!("/users/{}/trash", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let restore_resp = server
+            .post(&format!("/tabs/{}/restore", &user_tabs[0].id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let restored =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), user_tabs[0].id.clone()).await?;
+        let empty_trash_resp = server
+            .get(&format!("/users/{}/trash", &user_id))
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        assert_eq!(
+            listed_while_trashed
+                .results
+                .into_iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>(),
+            vec![user_tabs[1].id.clone()]
+        );
+        trash_resp.assert_status_ok();
+        let trash = trash_resp.json::<Trash>();
+        assert_eq!(trash.tabs.len(), 1);
+        assert_eq!(trash.tabs[0].id, user_tabs[0].id);
+        assert!(trash.tabs[0].deleted_at.is_some());
+        assert!(trash.tags.is_empty());
+        restore_resp.assert_status_ok();
+        assert_eq!(restore_resp.json::<Tab>().deleted_at, None);
+        assert_eq!(restored.tags, vec![tag]);
+        empty_trash_resp.assert_status_ok();
+        assert!(empty_trash_resp.json::<Trash>().tabs.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_restore_tab_conflict() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(trash_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let tab_data = || NewTab {
+            user_id: user_id.clone(),
+            url: "https://example.com/".into(),
+            notes: None,
+            title: None,
+        };
+        let trashed = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data()).await?;
+        let c = pool.get().await?;
+        tabs::delete_tab(c, user_id.clone(), trashed.id.clone()).await?;
+        // the trashed tab doesn't count as a duplicate
+        let resaved = tabs::new_tab(pool.clone(), &FakeMetadataFetcher, tab_data()).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .post(&format!("/tabs/{}/restore", &trashed.id))
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::CONFLICT);
+        assert_eq!(resp.json::<serde_json::Value>()["id"], resaved.id);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_restore_tab_not_trashed() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(trash_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let mut other_user_data = Faker.fake::<NewConfirmedUser>();
+        other_user_data.confirmed = true;
+        let c = pool.get().await?;
+        let other_user = users::new_user_confirmed(c, other_user_data).await?;
+        let other_user_id = other_user.id.clone();
+        let c = pool.get().await?;
+        let live_tab = bulk_create_tabs(c, user_id.clone(), 1).await?.remove(0);
+        let c = pool.get().await?;
+        let other_tab = bulk_create_tabs(c, other_user_id.clone(), 1)
+            .await?
+            .remove(0);
+        let c = pool.get().await?;
+        tabs::delete_tab(c, other_user_id.clone(), other_tab.id.clone()).await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let live_resp = server
+            .post(&format!("/tabs/{}/restore", &live_tab.id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let other_resp = server
+            .post(&format!("/tabs/{}/restore", &other_tab.id))
+            .add_header(header_name, header_value)
+            .await;
+        let c = pool.get().await?;
+        let other_trash = tabs::get_trashed_tabs(c, other_user_id.clone()).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, other_user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, other_user_id.clone()).await?;
+
+        live_resp.assert_status_not_found();
+        other_resp.assert_status_not_found();
+        assert_eq!(other_trash.len(), 1);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_trash_and_restore_tag() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(trash_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+        let c = pool.get().await?;
+        let tab = bulk_create_tabs(c, user_id.clone(), 1).await?.remove(0);
+        let c = pool.get().await?;
+        let tag = bulk_create_tags_from_strings(c, user_id.clone(), vec!["trashed".into()])
+            .await?
+            .remove(0);
+        let c = pool.get().await?;
+        tags::bulk_mk_tab_tags(
+            c,
+            vec![NewTabTag {
+                tab_id: tab.id.clone(),
+                tag_id: tag.id.clone(),
+            }],
+        )
+        .await?;
+        let c = pool.get().await?;
+        tags::delete_tag(c, user_id.clone(), tag.id.clone()).await?;
+        let while_trashed =
+            tabs::get_tab_with_tags(pool.clone(), user_id.clone(), tab.id.clone()).await?;
+        let filtered_while_trashed = tabs::get_user_tabs(
+            pool.clone(),
+            user_id.clone(),
+            TabFilter {
+                tag_ids: vec![tag.id.clone()],
+                ..Default::default()
+            },
+            PaginationRequest {
+                page: None,
+                page_size: None,
+                cursor: None,
+            },
+        )
+        .await?;
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let trash_resp = server
+            .get(&format!("/users/{}/trash", &user_id))
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let restore_resp = server
+            .post(&format!("/tags/{}/restore", &tag.id))
+            .add_header(header_name, header_value)
+            .await;
+        let restored = tabs::get_tab_with_tags(pool.clone(), user_id.clone(), tab.id).await?;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        assert!(while_trashed.tags.is_empty());
+        assert!(filtered_while_trashed.results.is_empty());
+        trash_resp.assert_status_ok();
+        let trash = trash_resp.json::<Trash>();
+        assert!(trash.tabs.is_empty());
+        assert_eq!(
+            trash.tags.into_iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![tag.id.clone()]
+        );
+        restore_resp.assert_status_ok();
+        assert_eq!(restore_resp.json::<Tag>(), tag);
+        assert_eq!(restored.tags, vec![tag]);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_trash_wrong_user() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let server = test_app(trash_router())?;
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let user_email = user.email.clone();
+
+        let session = sessions::new_session(pool.clone(), user_email, Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let resp = server
+            .get(&format!("/users/{}/trash", Faker.fake::<String>()))
+            .add_header(header_name, header_value)
+            .await;
+
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        resp.assert_status(StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_purge_trash() -> anyhow::Result<()> {
+        let pool = test_pool_from_env();
+        let mut user_data = Faker.fake::<NewConfirmedUser>();
+        user_data.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, user_data).await?;
+        let user_id = user.id.clone();
+        let c = pool.get().await?;
+        let user_tabs = bulk_create_tabs(c, user_id.clone(), 3).await?;
+        let c = pool.get().await?;
+        let user_tags =
+            bulk_create_tags_from_strings(c, user_id.clone(), vec!["old".into(), "new".into()])
+                .await?;
+        let c = pool.get().await?;
+        tabs::delete_tabs(
+            c,
+            user_id.clone(),
+            vec![user_tabs[0].id.clone(), user_tabs[1].id.clone()],
+        )
+        .await?;
+        for tag in user_tags.iter() {
+            let c = pool.get().await?;
+            tags::delete_tag(c, user_id.clone(), tag.id.clone()).await?;
+        }
+        // backdate one tab and one tag past the retention period
+        let long_ago = (chrono::Utc::now() - chrono::Duration::days(400)).naive_utc();
+        let (old_tab_id, old_tag_id) = (user_tabs[0].id.clone(), user_tags[0].id.clone());
+        let c = pool.get().await?;
+        c.interact(move |conn| {
+            diesel::update(tabs_dsl::tabs.filter(tabs_dsl::id.eq(old_tab_id)))
+                .set(tabs_dsl::deleted_at.eq(long_ago))
+                .execute(conn)?;
+            diesel::update(tags_dsl::tags.filter(tags_dsl::id.eq(old_tag_id)))
+                .set(tags_dsl::deleted_at.eq(long_ago))
+                .execute(conn)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))??;
+
+        let c = pool.get().await?;
+        let purged_tabs = tabs::purge_tabs(c, 365).await?;
+        let c = pool.get().await?;
+        let purged_tags = tags::purge_tags(c, 365).await?;
+        let c = pool.get().await?;
+        let trashed_tabs = tabs::get_trashed_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let trashed_tags = tags::get_trashed_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        let live_tab = tabs::get_tab(c, user_id.clone(), user_tabs[2].id.clone()).await;
+
+        let c = pool.get().await?;
+        tabs::delete_user_tabs(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        tags::delete_user_tags(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        assert!(purged_tabs >= 1);
+        assert!(purged_tags >= 1);
+        assert_eq!(
+            trashed_tabs.into_iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![user_tabs[1].id.clone()]
+        );
+        assert_eq!(
+            trashed_tags.into_iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![user_tags[1].id.clone()]
+        );
+        assert!(live_tab.is_ok());
+        Ok(())
+    }
+}
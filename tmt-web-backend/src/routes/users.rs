@@ -1,21 +1,23 @@
 #[cfg(test)]
 use crate::models::user::DeconfirmedUser;
 use crate::{
-    db::users,
+    db::{sessions, users, validate_password},
     models::{
         invite::{
             CreatedInvite, Invite, InviteUpdate, NewInvite, UserConfirmationPayload,
             UserInviteResponse,
         },
-        user::{CreatedUser, NewUser, User},
+        session::{Session, SessionSummary, SessionsDeletedResponse},
+        user::{CreatedUser, NewUser, PasswordChangePayload, User},
     },
+    passwords::check_password_policy,
     types::{AppError, AppState},
 };
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 
@@ -25,6 +27,9 @@ fn _users_router() -> Router<AppState> {
         .route("/users/invites/:invite_id", put(update_invite))
         .route("/users/invites/:invite_id", get(get_invite))
         .route("/users/:user_id", post(confirm_user))
+        .route("/users/:user_id/password", post(change_password))
+        .route("/users/:user_id/sessions", get(get_sessions))
+        .route("/users/:user_id/sessions", delete(revoke_sessions))
 }
 
 #[cfg(not(test))]
@@ -42,7 +47,7 @@ pub async fn create_user(
     Json(payload): Json<NewUser>,
 ) -> Result<impl IntoResponse, AppError> {
     let c = st.conn().await?;
-    let CreatedUser { id, email } = users::new_user(c, payload).await?;
+    let CreatedUser { id, email } = users::new_user(c, st.hasher(), payload).await?;
     let user_id = id.clone();
     let user_email = email.clone();
     let c = st.conn().await?;
@@ -89,6 +94,64 @@ pub async fn confirm_user(
     Ok(Json(user))
 }
 
+// keeps the session making the change, logs out every other one
+pub async fn change_password(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+    Json(payload): Json<PasswordChangePayload>,
+) -> Result<StatusCode, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    check_password_policy(&payload.new_password)?;
+    let conn = st.conn().await?;
+    let user = users::get_user(conn, session.user_id.clone()).await?;
+    let conn = st.conn().await?;
+    if !validate_password(conn, st.hasher(), user.email, payload.current_password).await? {
+        return Err(AppError::WrongCredentials);
+    }
+    let hash = st.hasher().hash(payload.new_password).await?;
+    let conn = st.conn().await?;
+    users::change_password(conn, session.user_id, hash, session.id).await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn get_sessions(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<Vec<SessionSummary>>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let sessions = sessions::get_user_sessions(conn, user_id).await?;
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|s| SessionSummary {
+                current: s.id == session.id,
+                session: s,
+            })
+            .collect(),
+    ))
+}
+
+// log out everywhere, this device included
+pub async fn revoke_sessions(
+    State(st): State<AppState>,
+    session: Session,
+    Path(user_id): Path<String>,
+) -> Result<Json<SessionsDeletedResponse>, AppError> {
+    if user_id != session.user_id {
+        return Err(AppError::WrongCredentials);
+    }
+    let conn = st.conn().await?;
+    let deleted = sessions::revoke_user_sessions(conn, user_id).await?;
+    Ok(Json(SessionsDeletedResponse { deleted }))
+}
+
 // cfg(test) for now, needs better security
 #[cfg(test)]
 pub async fn deconfirm_user(
@@ -105,6 +168,7 @@ mod tests {
     use super::*;
     use crate::{
         models::{invite::InviteStatus, user::NewConfirmedUser},
+        passwords::PasswordHasher,
         routes::_test_utils::test_app,
         types::test_pool_from_env,
     };
@@ -118,6 +182,7 @@ mod tests {
         let server = test_app(users_router())?;
 
         let create_user_data = Faker.fake::<NewUser>();
+        let pwd = create_user_data.password.clone();
         let resp = server.post("/users").json(&json!(create_user_data)).await;
 
         resp.assert_status(StatusCode::CREATED);
@@ -130,6 +195,12 @@ mod tests {
         let user_email = user.email.clone();
         let user_id = user.id.clone();
         assert_eq!(user_email.clone(), resp_data.email);
+        assert!(user.password.starts_with("$argon2id$"));
+        assert!(
+            PasswordHasher::for_tests()
+                .verify(pwd, user.password.clone())
+                .await?
+        );
         let c = pool.get().await?;
         let ci = users::get_invite(c, resp_data.invite_id).await?;
         assert_eq!(ci.user_id, user_id.clone());
@@ -149,7 +220,7 @@ mod tests {
         let cud_1 = Faker.fake::<NewUser>();
         let email = cud_1.email.clone();
         let c = pool.get().await?;
-        let _existing_user = users::new_user(c, cud_1).await?;
+        let _existing_user = users::new_user(c, &PasswordHasher::for_tests(), cud_1).await?;
 
         let server = test_app(users_router())?;
 
@@ -167,7 +238,8 @@ mod tests {
         let pool = test_pool_from_env();
         let ud = Faker.fake::<NewUser>();
         let c = pool.get().await?;
-        let CreatedUser { id, email } = users::new_user(c, ud).await?;
+        let CreatedUser { id, email } =
+            users::new_user(c, &PasswordHasher::for_tests(), ud).await?;
         let user_id = id.clone();
         let user_email = email.clone();
         let inv = NewInvite {
@@ -216,7 +288,8 @@ mod tests {
         let pool = test_pool_from_env();
         let ud = Faker.fake::<NewUser>();
         let c = pool.get().await?;
-        let CreatedUser { id, email } = users::new_user(c, ud).await?;
+        let CreatedUser { id, email } =
+            users::new_user(c, &PasswordHasher::for_tests(), ud).await?;
         let user_id = id.clone();
         let inv = NewInvite {
             user_id: user_id.clone(),
@@ -242,7 +315,8 @@ mod tests {
         let pool = test_pool_from_env();
         let ud = Faker.fake::<NewUser>();
         let c = pool.get().await?;
-        let CreatedUser { id, email } = users::new_user(c, ud).await?;
+        let CreatedUser { id, email } =
+            users::new_user(c, &PasswordHasher::for_tests(), ud).await?;
         let user_id = id.clone();
         let inv = NewInvite {
             user_id: user_id.clone(),
@@ -266,9 +340,11 @@ mod tests {
         let pool = test_pool_from_env();
         let ud = Faker.fake::<NewUser>();
         let c = pool.get().await?;
-        let cu = users::new_user(c, ud).await?;
+        let cu = users::new_user(c, &PasswordHasher::for_tests(), ud).await?;
         let user_id = cu.id.clone();
         let user_email = cu.email.clone();
+        let c = pool.get().await?;
+        let before = users::get_user(c, user_id.clone()).await?;
         let inv = NewInvite {
             user_id: user_id.clone(),
             email: user_email.clone(),
@@ -293,6 +369,8 @@ mod tests {
         let confirmed_user = resp.json::<User>();
         assert_eq!(confirmed_user.id, user_id.clone());
         assert!(confirmed_user.confirmed);
+        // confirming used to hash the hash again
+        assert_eq!(confirmed_user.password, before.password);
         Ok(())
     }
     #[test_log::test(tokio::test)]
@@ -301,7 +379,7 @@ mod tests {
         let pool = test_pool_from_env();
         let ud = Faker.fake::<NewUser>();
         let c = pool.get().await?;
-        let cu = users::new_user(c, ud).await?;
+        let cu = users::new_user(c, &PasswordHasher::for_tests(), ud).await?;
         let user_id = cu.id.clone();
         let user_email = cu.email.clone();
         let inv = NewInvite {
@@ -364,4 +442,162 @@ mod tests {
         resp.assert_status(StatusCode::NOT_FOUND);
         Ok(())
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_change_password() -> anyhow::Result<()> {
+        use crate::{
+            db::sessions::{delete_user_sessions, get_session, new_session},
+            types::Claims,
+        };
+        use http::header;
+
+        let server = test_app(users_router())?;
+        let pool = test_pool_from_env();
+        let mut ud = Faker.fake::<NewConfirmedUser>();
+        ud.confirmed = true;
+        let old_pwd = ud.password.clone();
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, ud).await?;
+        let user_id = user.id.clone();
+        let session = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let other = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let token = Claims::from_session(&session).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let new_pwd = "a much better password".to_string();
+        let url = format!("/users/{}/password", &user_id);
+
+        let wrong_user = server
+            .post("/users/not-my-id/password")
+            .json(&PasswordChangePayload {
+                current_password: old_pwd.clone(),
+                new_password: new_pwd.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let wrong_pwd = server
+            .post(&url)
+            .json(&PasswordChangePayload {
+                current_password: "bad_password".into(),
+                new_password: new_pwd.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let too_short = server
+            .post(&url)
+            .json(&PasswordChangePayload {
+                current_password: old_pwd.clone(),
+                new_password: "short".into(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let changed = server
+            .post(&url)
+            .json(&PasswordChangePayload {
+                current_password: old_pwd.clone(),
+                new_password: new_pwd.clone(),
+            })
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let kept = get_session(c, session.id.clone()).await?;
+        let c = pool.get().await?;
+        let revoked = get_session(c, other.id.clone()).await?;
+        let hasher = PasswordHasher::for_tests();
+        let c = pool.get().await?;
+        let old_valid = validate_password(c, &hasher, user.email.clone(), old_pwd).await?;
+        let c = pool.get().await?;
+        let new_valid = validate_password(c, &hasher, user.email.clone(), new_pwd).await?;
+
+        let c = pool.get().await?;
+        delete_user_sessions(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        wrong_user.assert_status(StatusCode::FORBIDDEN);
+        wrong_pwd.assert_status(StatusCode::FORBIDDEN);
+        too_short.assert_status(StatusCode::BAD_REQUEST);
+        changed.assert_status_ok();
+        assert!(kept.is_some());
+        assert!(revoked.is_none());
+        assert!(!old_valid);
+        assert!(new_valid);
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_user_sessions() -> anyhow::Result<()> {
+        use crate::{
+            db::sessions::{delete_user_sessions, get_session, new_session},
+            models::session::ClientInfo,
+            types::Claims,
+        };
+        use http::header;
+
+        let server = test_app(users_router())?;
+        let pool = test_pool_from_env();
+        let mut ud = Faker.fake::<NewConfirmedUser>();
+        ud.confirmed = true;
+        let c = pool.get().await?;
+        let user = users::new_user_confirmed(c, ud).await?;
+        let user_id = user.id.clone();
+        let laptop = new_session(pool.clone(), user.email.clone(), Default::default()).await?;
+        let phone = new_session(
+            pool.clone(),
+            user.email.clone(),
+            ClientInfo {
+                user_agent: Some("phone".into()),
+                ip: Some("192.0.2.1".into()),
+            },
+        )
+        .await?;
+        let token = Claims::from_session(&laptop).test_to_token()?;
+        let bearer = format!("Bearer {}", token);
+        let header_value = header::HeaderValue::from_str(&bearer)?;
+        let header_name = header::AUTHORIZATION;
+        let url = format!("/users/{}/sessions", &user_id);
+
+        let wrong_user = server
+            .get("/users/not-my-id/sessions")
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let listed = server
+            .get(&url)
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let everywhere = server
+            .delete(&url)
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let after = server
+            .get(&url)
+            .add_header(header_name.clone(), header_value.clone())
+            .await;
+        let c = pool.get().await?;
+        let phone_after = get_session(c, phone.id.clone()).await?;
+
+        let c = pool.get().await?;
+        delete_user_sessions(c, user_id.clone()).await?;
+        let c = pool.get().await?;
+        users::deconfirm_user(c, user_id.clone()).await?;
+
+        wrong_user.assert_status(StatusCode::FORBIDDEN);
+        listed.assert_status_ok();
+        let listed = listed.json::<Vec<SessionSummary>>();
+        assert_eq!(listed.len(), 2);
+        let listed_phone = listed.iter().find(|s| s.session.id == phone.id).unwrap();
+        assert!(!listed_phone.current);
+        assert_eq!(listed_phone.session.user_agent.as_deref(), Some("phone"));
+        assert_eq!(listed_phone.session.ip.as_deref(), Some("192.0.2.1"));
+        assert!(listed
+            .iter()
+            .any(|s| s.session.id == laptop.id && s.current));
+        everywhere.assert_status_ok();
+        assert_eq!(everywhere.json::<SessionsDeletedResponse>().deleted, 2);
+        assert!(phone_after.is_none());
+        // the calling session went too
+        after.assert_status(StatusCode::BAD_REQUEST);
+        Ok(())
+    }
 }
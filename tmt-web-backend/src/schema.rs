@@ -4,6 +4,14 @@ pub mod sql_types {
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "invite_status"))]
     pub struct InviteStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tab_status"))]
+    pub struct TabStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
 }
 
 diesel::table! {
@@ -19,15 +27,52 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    password_resets (id) {
+        id -> Text,
+        user_id -> Text,
+        expires -> Timestamp,
+        token_hash -> Text,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Text,
+        session_id -> Text,
+        created_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        token_hash -> Text,
+    }
+}
+
 diesel::table! {
     sessions (id) {
         id -> Text,
         user_id -> Text,
         expires -> Timestamp,
+        user_agent -> Nullable<Text>,
+        ip -> Nullable<Text>,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tab_groups (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        position -> Int4,
+        created_at -> Timestamp,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+    use super::sql_types::TabStatus;
+
     tabs (id) {
         id -> Text,
         user_id -> Text,
@@ -35,6 +80,17 @@ diesel::table! {
         notes -> Nullable<Text>,
         created_at -> Timestamp,
         modified_at -> Timestamp,
+        title -> Nullable<Text>,
+        description -> Nullable<Text>,
+        favicon_url -> Nullable<Text>,
+        canonical_url -> Nullable<Text>,
+        search -> Nullable<Tsvector>,
+        normalized_url -> Nullable<Text>,
+        domain -> Nullable<Text>,
+        status -> TabStatus,
+        deleted_at -> Nullable<Timestamp>,
+        group_id -> Nullable<Text>,
+        group_position -> Nullable<Int4>,
     }
 }
 
@@ -50,6 +106,12 @@ diesel::table! {
         id -> Text,
         user_id -> Text,
         tag -> Text,
+        created_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+        parent_id -> Nullable<Text>,
+        color -> Nullable<Text>,
+        description -> Nullable<Text>,
+        pinned -> Bool,
     }
 }
 
@@ -63,7 +125,11 @@ diesel::table! {
 }
 
 diesel::joinable!(invites -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(refresh_tokens -> sessions (session_id));
 diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(tab_groups -> users (user_id));
+diesel::joinable!(tabs -> tab_groups (group_id));
 diesel::joinable!(tabs -> users (user_id));
 diesel::joinable!(tabs_tags -> tabs (tab_id));
 diesel::joinable!(tabs_tags -> tags (tag_id));
@@ -71,7 +137,10 @@ diesel::joinable!(tags -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     invites,
+    password_resets,
+    refresh_tokens,
     sessions,
+    tab_groups,
     tabs,
     tabs_tags,
     tags,
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::{
+    db::{tabs, tags},
+    types::{AppError, AppState},
+};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Empties the trash of anything older than the configured retention, once at startup and
+// hourly after that.
+pub fn spawn_trash_purge(st: AppState) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_trash(&st).await {
+                Ok((0, 0)) => {}
+                Ok((tabs, tags)) => tracing::info!("purged {} tabs and {} tags", tabs, tags),
+                Err(e) => tracing::error!("error purging trash: {:?}", e),
+            }
+        }
+    })
+}
+
+pub async fn purge_trash(st: &AppState) -> Result<(usize, usize), AppError> {
+    let days = st.trash_retention_days();
+    let purged_tabs = tabs::purge_tabs(st.conn().await?, days).await?;
+    let purged_tags = tags::purge_tags(st.conn().await?, days).await?;
+    Ok((purged_tabs, purged_tags))
+}
@@ -1,5 +1,16 @@
 use crate::auth::{encode_jwt, get_claims};
-use crate::models::session::Session;
+#[cfg(test)]
+use crate::mailer::FakeMailer;
+#[cfg(not(test))]
+use crate::mailer::LogMailer;
+use crate::mailer::Mailer;
+#[cfg(test)]
+use crate::metadata::FakeMetadataFetcher;
+#[cfg(not(test))]
+use crate::metadata::HttpMetadataFetcher;
+use crate::metadata::MetadataFetcher;
+use crate::models::{session::Session, tab::TabStatus};
+use crate::passwords::PasswordHasher;
 use axum::{
     async_trait,
     extract::{FromRef, FromRequestParts},
@@ -7,25 +18,47 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use chrono::{DateTime, Utc};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use deadpool_diesel::postgres;
 use dotenvy::dotenv;
 use jsonwebtoken::{DecodingKey, EncodingKey};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::json;
-use std::{convert::Infallible, env};
+use std::{convert::Infallible, env, fmt, net::IpAddr, sync::Arc};
 
 #[derive(Clone)]
 pub struct AppState {
     pool: postgres::Pool,
     keys: Keys,
+    fetcher: Arc<dyn MetadataFetcher>,
+    mailer: Arc<dyn Mailer>,
+    trash_retention_days: i32,
+    hasher: PasswordHasher,
+    // proxies whose `X-Forwarded-For` is believed
+    trusted_proxies: Arc<Vec<IpAddr>>,
 }
 
+const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+
 impl AppState {
-    fn new(secret: &[u8], db_url: String) -> Self {
+    fn new(
+        secret: &[u8],
+        db_url: String,
+        fetcher: Arc<dyn MetadataFetcher>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
         let keys = Keys::new(secret);
         let pool = make_pool(db_url);
-        Self { pool, keys }
+        Self {
+            pool,
+            keys,
+            fetcher,
+            mailer,
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            hasher: PasswordHasher::default(),
+            trusted_proxies: Arc::new(Vec::new()),
+        }
     }
     #[cfg(not(test))]
     pub fn from_env() -> Self {
@@ -34,7 +67,32 @@ impl AppState {
         tracing::info!("loading env");
         let secret = env::var("JWT_SECRET").expect("missing JWT_SECRET");
         let db_url = env::var("DATABASE_URL").expect("missing DATABASE_URL");
-        Self::new(secret.as_bytes(), db_url)
+        // anything but an explicit development env refuses to fetch private addresses
+        let is_dev = env::var("APP_ENV").is_ok_and(|e| e == "development");
+        let fetcher = Arc::new(HttpMetadataFetcher::new(is_dev));
+        let mut st = Self::new(secret.as_bytes(), db_url, fetcher, Arc::new(LogMailer));
+        if let Some(days) = env::var("TRASH_RETENTION_DAYS")
+            .ok()
+            .filter(|d| !d.is_empty())
+        {
+            st.trash_retention_days = days
+                .parse()
+                .ok()
+                .filter(|d| *d >= 0)
+                .expect("invalid TRASH_RETENTION_DAYS");
+        }
+        st.hasher = PasswordHasher::from_env();
+        if let Ok(proxies) = env::var("TRUSTED_PROXIES") {
+            st.trusted_proxies = Arc::new(
+                proxies
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.parse().expect("invalid TRUSTED_PROXIES"))
+                    .collect(),
+            );
+        }
+        st
     }
     #[cfg(test)]
     pub fn from_env() -> Self {
@@ -43,7 +101,14 @@ impl AppState {
         tracing::info!("loading test env");
         let secret = env::var("JWT_SECRET_TEST").expect("missing JWT_SECRET_TEST");
         let db_url = env::var("DATABASE_URL_TEST").expect("missing DATABASE_URL_TEST");
-        Self::new(secret.as_bytes(), db_url)
+        let mut st = Self::new(
+            secret.as_bytes(),
+            db_url,
+            Arc::new(FakeMetadataFetcher),
+            Arc::new(FakeMailer),
+        );
+        st.hasher = PasswordHasher::for_tests();
+        st
     }
     pub fn encoding(&self) -> &EncodingKey {
         self.keys.encoding()
@@ -51,6 +116,21 @@ impl AppState {
     pub fn decoding(&self) -> &DecodingKey {
         self.keys.decoding()
     }
+    pub fn fetcher(&self) -> &dyn MetadataFetcher {
+        self.fetcher.as_ref()
+    }
+    pub fn mailer(&self) -> &dyn Mailer {
+        self.mailer.as_ref()
+    }
+    pub fn hasher(&self) -> &PasswordHasher {
+        &self.hasher
+    }
+    pub fn trusted_proxies(&self) -> &[IpAddr] {
+        &self.trusted_proxies
+    }
+    pub fn trash_retention_days(&self) -> i32 {
+        self.trash_retention_days
+    }
     pub fn pool(&self) -> postgres::Pool {
         self.pool.clone()
     }
@@ -102,12 +182,17 @@ pub struct Claims {
     pub(crate) exp: i64,
 }
 
+// Access tokens are short-lived; clients get new ones with their refresh token
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+
 impl Claims {
+    // never outlives the session itself
     pub fn from_session(session: &Session) -> Self {
+        let exp = Utc::now().timestamp() + ACCESS_TOKEN_TTL_SECS;
         Self {
             sub: session.user_id.clone(),
             jti: session.id.clone(),
-            exp: session.expires.and_utc().timestamp(),
+            exp: exp.min(session.expires.and_utc().timestamp()),
         }
     }
 
@@ -137,20 +222,50 @@ where
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct AuthBody {
     pub access_token: String,
     pub token_type: String,
+    // seconds until `access_token` expires
+    pub expires_in: i64,
+    pub refresh_token: String,
 }
 impl AuthBody {
-    pub(crate) fn new(access_token: String) -> Self {
+    pub(crate) fn new(access_token: String, expires_in: i64, refresh_token: String) -> Self {
         Self {
             access_token,
             token_type: "Bearer".to_string(),
+            expires_in,
+            refresh_token,
         }
     }
 }
 
+// the tokens are credentials, keep them out of the logs
+impl fmt::Debug for AuthBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthBody")
+            .field("access_token", &"[redacted]")
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field("refresh_token", &"[redacted]")
+            .finish()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+impl fmt::Debug for RefreshRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshRequest")
+            .field("refresh_token", &"[redacted]")
+            .finish()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AuthPayload {
     pub client_id: String,
@@ -161,6 +276,9 @@ pub struct AuthPayload {
 pub struct PaginationRequest {
     pub page: Option<i64>,
     pub page_size: Option<i64>,
+    // any `cursor` (even an empty one, for the first page) switches to keyset pagination
+    // and `page` is ignored
+    pub cursor: Option<String>,
 }
 
 impl PaginationRequest {
@@ -173,17 +291,318 @@ impl PaginationRequest {
     pub fn offset(&self) -> i64 {
         (self.page() - 1) * self.limit()
     }
+    pub fn decode_cursor(&self) -> Result<Option<Cursor>, AppError> {
+        match self.cursor.as_deref() {
+            None | Some("") => Ok(None),
+            Some(c) => Cursor::decode(c).map(Some),
+        }
+    }
+}
+
+// position of the last row of a page, opaque to clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub key: CursorKey,
+    pub id: String,
+}
+
+// the value of the sort column, which the cursor is only valid for
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorKey {
+    CreatedAt(NaiveDateTime),
+    ModifiedAt(NaiveDateTime),
+    Url(String),
+    // tags: pinned ones first, then by creation
+    Pinned(bool, NaiveDateTime),
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let micros = |ts: &NaiveDateTime| ts.and_utc().timestamp_micros().to_string();
+        let (name, value) = match &self.key {
+            CursorKey::CreatedAt(ts) => ("created_at", micros(ts)),
+            CursorKey::ModifiedAt(ts) => ("modified_at", micros(ts)),
+            CursorKey::Url(url) => ("url", url.clone()),
+            CursorKey::Pinned(pinned, ts) => {
+                ("pinned", format!("{}:{}", *pinned as u8, micros(ts)))
+            }
+        };
+        // the value goes last, urls can contain `:`
+        URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", name, self.id, value))
+    }
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let raw = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|b| String::from_utf8(b).ok())
+            .ok_or(AppError::BadRequest)?;
+        let mut parts = raw.splitn(3, ':');
+        let (name, id, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(id), Some(value)) => (name, id, value),
+            _ => return Err(AppError::BadRequest),
+        };
+        let timestamp = |value: &str| {
+            value
+                .parse::<i64>()
+                .ok()
+                .and_then(DateTime::from_timestamp_micros)
+                .map(|dt| dt.naive_utc())
+                .ok_or(AppError::BadRequest)
+        };
+        let key = match name {
+            "created_at" => CursorKey::CreatedAt(timestamp(value)?),
+            "modified_at" => CursorKey::ModifiedAt(timestamp(value)?),
+            "url" => CursorKey::Url(value.to_string()),
+            "pinned" => match value.split_once(':') {
+                Some(("1", ts)) => CursorKey::Pinned(true, timestamp(ts)?),
+                Some(("0", ts)) => CursorKey::Pinned(false, timestamp(ts)?),
+                _ => return Err(AppError::BadRequest),
+            },
+            _ => return Err(AppError::BadRequest),
+        };
+        Ok(Self {
+            key,
+            id: id.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatchMode {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TabSort {
+    #[default]
+    CreatedAt,
+    ModifiedAt,
+    Url,
+}
+
+impl TabSort {
+    // newest first for dates, alphabetical for urls
+    pub fn default_direction(&self) -> SortDirection {
+        match self {
+            TabSort::CreatedAt | TabSort::ModifiedAt => SortDirection::Desc,
+            TabSort::Url => SortDirection::Asc,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagSort {
+    // reverse alphabetical
+    #[default]
+    Tag,
+    // most tabs first, ties alphabetical
+    Usage,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TabListRequest {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub cursor: Option<String>,
+    // comma-separated tag ids
+    pub tags: Option<String>,
+    pub mode: Option<TagMatchMode>,
+    pub sort: Option<TabSort>,
+    pub direction: Option<SortDirection>,
+    // RFC 3339 timestamps or plain dates (midnight UTC)
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub domain: Option<String>,
+    // comma-separated statuses, unread and read by default
+    pub status: Option<String>,
+}
+
+impl TabListRequest {
+    pub fn pagination(&self) -> PaginationRequest {
+        PaginationRequest {
+            page: self.page,
+            page_size: self.page_size,
+            cursor: self.cursor.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TagListRequest {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    // only for the default sort
+    pub cursor: Option<String>,
+    pub sort: Option<TagSort>,
+}
+
+impl TagListRequest {
+    pub fn pagination(&self) -> PaginationRequest {
+        PaginationRequest {
+            page: self.page,
+            page_size: self.page_size,
+            cursor: self.cursor.clone(),
+        }
+    }
+}
+
+// a validated `TabListRequest`
+#[derive(Debug, Default)]
+pub struct TabFilter {
+    pub tag_ids: Vec<String>,
+    pub tag_mode: TagMatchMode,
+    pub sort: TabSort,
+    pub direction: SortDirection,
+    // inclusive
+    pub created_after: Option<NaiveDateTime>,
+    // exclusive
+    pub created_before: Option<NaiveDateTime>,
+    // matches subdomains too
+    pub domain: Option<String>,
+    // `None` matches every status
+    pub statuses: Option<Vec<TabStatus>>,
+}
+
+impl TryFrom<&TabListRequest> for TabFilter {
+    type Error = AppError;
+
+    fn try_from(lr: &TabListRequest) -> Result<Self, Self::Error> {
+        let mut tag_ids: Vec<String> = Vec::new();
+        for tag_id in lr.tags.iter().flat_map(|ts| ts.split(',')) {
+            let tag_id = tag_id.trim();
+            if !tag_id.is_empty() && !tag_ids.iter().any(|t| t == tag_id) {
+                tag_ids.push(tag_id.to_string());
+            }
+        }
+        let created_after = lr
+            .created_after
+            .as_deref()
+            .map(parse_date_param)
+            .transpose()?;
+        let created_before = lr
+            .created_before
+            .as_deref()
+            .map(parse_date_param)
+            .transpose()?;
+        if let (Some(after), Some(before)) = (created_after, created_before) {
+            if after >= before {
+                return Err(AppError::BadRequest);
+            }
+        }
+        // colons only inside the brackets of an IPv6 host
+        let invalid_domain = |d: &str| {
+            let (host, forbidden) = match d.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+                Some(v6) => (v6, "/?#@[]"),
+                None => (d, "/:?#@"),
+            };
+            host.contains(|c: char| c.is_whitespace() || forbidden.contains(c))
+        };
+        let domain = match lr.domain.as_deref().map(|d| d.trim().trim_matches('.')) {
+            None | Some("") => None,
+            Some(d) if invalid_domain(d) => return Err(AppError::BadRequest),
+            Some(d) => Some(d.to_lowercase()),
+        };
+        let statuses = match lr.status.as_deref() {
+            None => vec![TabStatus::Unread, TabStatus::Read],
+            Some(ss) => {
+                let mut statuses = Vec::new();
+                for s in ss.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    let status = s.parse().map_err(|_| AppError::BadRequest)?;
+                    if !statuses.contains(&status) {
+                        statuses.push(status);
+                    }
+                }
+                if statuses.is_empty() {
+                    return Err(AppError::BadRequest);
+                }
+                statuses
+            }
+        };
+        let sort = lr.sort.unwrap_or_default();
+        Ok(Self {
+            tag_ids,
+            tag_mode: lr.mode.unwrap_or_default(),
+            sort,
+            direction: lr.direction.unwrap_or(sort.default_direction()),
+            created_after,
+            created_before,
+            domain,
+            statuses: Some(statuses),
+        })
+    }
+}
+
+fn parse_date_param(param: &str) -> Result<NaiveDateTime, AppError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(param) {
+        return Ok(dt.naive_utc());
+    }
+    NaiveDate::parse_from_str(param, "%Y-%m-%d")
+        .map(|d| d.and_time(NaiveTime::MIN))
+        .map_err(|_| AppError::BadRequest)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TabSearchRequest {
+    pub q: String,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+impl TabSearchRequest {
+    // results are ranked, so there's no cursor mode
+    pub fn pagination(&self) -> PaginationRequest {
+        PaginationRequest {
+            page: self.page,
+            page_size: self.page_size,
+            cursor: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PaginatedResult<T> {
     pub results: Vec<T>,
     pub has_more: bool,
+    // only in cursor mode, absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> PaginatedResult<T> {
+    // `results` should hold one row more than `limit` if there is a next page
+    pub fn from_keyset(mut results: Vec<T>, limit: i64, cursor_for: impl Fn(&T) -> Cursor) -> Self {
+        let has_more = results.len() as i64 > limit;
+        results.truncate(limit.max(0) as usize);
+        let next_cursor = if has_more {
+            results.last().map(|r| cursor_for(r).encode())
+        } else {
+            None
+        };
+        Self {
+            results,
+            has_more,
+            next_cursor,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MatchFragmentRequest {
     pub fragment: String,
+    pub limit: Option<i64>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -218,6 +637,8 @@ pub enum AppError {
     NotFound,
     DBErrorWithMessage(String),
     BadRequest,
+    // carries the id of the existing row
+    Conflict(String),
 }
 
 impl AppError {
@@ -244,6 +665,7 @@ impl AppError {
                 (StatusCode::BAD_REQUEST, err_msg)
             }
             AppError::BadRequest => (StatusCode::BAD_REQUEST, "Invalid request".into()),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, "Already exists".into()),
         }
     }
 }
@@ -260,13 +682,28 @@ impl std::error::Error for AppError {}
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = self.to_status_message();
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        let body = match self {
+            AppError::Conflict(id) => Json(json!({
+                "error": error_message,
+                "id": id,
+            })),
+            _ => Json(json!({
+                "error": error_message,
+            })),
+        };
         (status, body).into_response()
     }
 }
 
+// distinguishes an explicit `null` (`Some(None)`) from a missing field (`None`)
+pub(crate) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).map(Some)
+}
+
 pub(crate) fn make_pool(db_url: String) -> postgres::Pool {
     let manager = postgres::Manager::new(db_url, deadpool_diesel::Runtime::Tokio1);
     postgres::Pool::builder(manager).build().unwrap()
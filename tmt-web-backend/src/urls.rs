@@ -0,0 +1,67 @@
+use url::Url;
+
+// query parameters that only exist to track where a click came from
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "mc_cid",
+    "mc_eid", "_hsenc", "_hsmi", "ref_src",
+];
+
+/// The form of a url used to detect duplicate tabs: lowercased scheme and host, no default
+/// port, no fragment, no tracking parameters and no trailing slash (except on the root path).
+/// Anything that doesn't parse as an absolute url is only trimmed.
+pub fn normalize_url(url: &str) -> String {
+    let trimmed = url.trim();
+    let mut parsed = match Url::parse(trimmed) {
+        Ok(u) => u,
+        Err(_) => return trimmed.to_string(),
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return trimmed.to_string();
+    }
+    parsed.set_fragment(None);
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking_param(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    let path = parsed.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        parsed.set_path(path.trim_end_matches('/'));
+    }
+    parsed.into()
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        for (input, expected) in [
+            ("https://Example.COM", "https://example.com/"),
+            ("HTTPS://example.com:443/a/", "https://example.com/a"),
+            ("http://example.com:8080/a//", "http://example.com:8080/a"),
+            ("https://example.com/a#section", "https://example.com/a"),
+            (
+                "https://example.com/a?utm_source=x&id=3&UTM_medium=y&fbclid=z",
+                "https://example.com/a?id=3",
+            ),
+            ("https://example.com/?gclid=1", "https://example.com/"),
+            ("  https://example.com/Path  ", "https://example.com/Path"),
+            ("not a url ", "not a url"),
+            ("mailto:Someone@Example.com", "mailto:Someone@Example.com"),
+        ] {
+            assert_eq!(normalize_url(input), expected, "{}", input);
+        }
+    }
+}